@@ -0,0 +1,255 @@
+//! Construct SSA form from mutable variables.
+//!
+//! This implements the on-the-fly algorithm from Braun et al., "Simple and Efficient Construction of Static Single Assignment Form".
+
+use std::{
+    collections::{HashMap, HashSet},
+    ptr::NonNull,
+};
+
+use crate::{Block, Func, InstData, InstRef, Ty, ffi};
+
+/// A mutable variable declared with [`FunctionBuilder::declare_var`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Variable(usize);
+
+/// Wraps a [`Func`] to turn variable definitions and uses into phis and upsilons.
+///
+/// Blocks must be terminated with [`FunctionBuilder::push_jump`] and [`FunctionBuilder::push_branch`] rather than the methods on [`Block`], so the builder knows their predecessors without rescanning the function.
+///
+/// A block must be sealed with [`FunctionBuilder::seal_block`] once all of its predecessors have been terminated. Until then, uses of a variable in that block produce phis whose upsilons are only filled in upon sealing.
+///
+/// Phis which turn out to have a single value besides themselves are removed, so the value returned by [`FunctionBuilder::use_var`] may stop being a phi once its block's predecessors are sealed. Instructions the builder replaces this way must not be used afterwards.
+#[derive(Debug)]
+pub struct FunctionBuilder<'module, 'func> {
+    func: Func<'module, 'func>,
+    var_tys: Vec<Ty>,
+    defs: HashMap<(Variable, NonNull<ffi::Block>), InstRef<'func>>,
+    sealed: HashSet<NonNull<ffi::Block>>,
+    incomplete_phis: HashMap<NonNull<ffi::Block>, Vec<(Variable, InstRef<'func>)>>,
+    predecessors: HashMap<NonNull<ffi::Block>, Vec<Block<'module, 'func>>>,
+    upsilons: HashMap<NonNull<ffi::Inst>, Vec<(Block<'module, 'func>, InstRef<'func>)>>,
+    replaced: HashMap<NonNull<ffi::Inst>, InstRef<'func>>,
+}
+
+impl<'module, 'func> FunctionBuilder<'module, 'func> {
+    #[must_use]
+    pub fn new(func: Func<'module, 'func>) -> Self {
+        Self {
+            func,
+            var_tys: vec![],
+            defs: HashMap::new(),
+            sealed: HashSet::new(),
+            incomplete_phis: HashMap::new(),
+            predecessors: HashMap::new(),
+            upsilons: HashMap::new(),
+            replaced: HashMap::new(),
+        }
+    }
+
+    pub fn func(&self) -> Func<'module, 'func> {
+        self.func
+    }
+
+    pub fn declare_var(&mut self, ty: Ty) -> Variable {
+        let var = Variable(self.var_tys.len());
+        self.var_tys.push(ty);
+        var
+    }
+
+    pub fn def_var(&mut self, var: Variable, block: Block<'module, 'func>, value: InstRef<'func>) {
+        let var_ty = self.var_tys[var.0];
+        assert_eq!(
+            value.ty(),
+            var_ty,
+            "variable has type {var_ty:?} but was defined with a value of type {:?}",
+            value.ty()
+        );
+        self.defs.insert((var, block.inner), value);
+    }
+
+    /// Get the value of `var` as last defined in `block`, inserting phis as necessary.
+    pub fn use_var(&mut self, var: Variable, block: Block<'module, 'func>) -> InstRef<'func> {
+        if let Some(&value) = self.defs.get(&(var, block.inner)) {
+            return value;
+        }
+        let ty = self.var_tys[var.0];
+        let value = if !self.sealed.contains(&block.inner) {
+            // We don't know all the predecessors yet, so fill this phi in once we do.
+            let phi = block.push_phi(ty);
+            self.incomplete_phis
+                .entry(block.inner)
+                .or_default()
+                .push((var, phi));
+            phi
+        } else {
+            match self.predecessors(block) {
+                [] => panic!("variable used before it was defined"),
+                &[predecessor] => self.use_var(var, predecessor),
+                _ => {
+                    // Define the phi before recursing, in case of cycles back to this block.
+                    let phi = block.push_phi(ty);
+                    self.defs.insert((var, block.inner), phi);
+                    self.add_phi_operands(var, phi, block)
+                }
+            }
+        };
+        self.defs.insert((var, block.inner), value);
+        value
+    }
+
+    /// Declare that all predecessors of `block` have been terminated.
+    pub fn seal_block(&mut self, block: Block<'module, 'func>) {
+        let newly_sealed = self.sealed.insert(block.inner);
+        assert!(newly_sealed, "block was already sealed");
+        for (var, phi) in self
+            .incomplete_phis
+            .remove(&block.inner)
+            .unwrap_or_default()
+        {
+            self.add_phi_operands(var, phi, block);
+        }
+    }
+
+    /// Terminate `block` with a jump to `dest`, which must not be sealed yet.
+    pub fn push_jump(&mut self, block: Block<'module, 'func>, dest: Block<'module, 'func>) {
+        self.add_predecessor(dest, block);
+        block.push_jump(dest);
+    }
+
+    /// Terminate `block` with a branch on `cond`, neither of whose targets may be sealed yet.
+    pub fn push_branch(
+        &mut self,
+        block: Block<'module, 'func>,
+        cond: InstRef<'func>,
+        then_block: Block<'module, 'func>,
+        else_block: Block<'module, 'func>,
+    ) {
+        self.add_predecessor(then_block, block);
+        if else_block != then_block {
+            self.add_predecessor(else_block, block);
+        }
+        block.push_branch(cond, then_block, else_block);
+    }
+
+    pub fn seal_all_blocks(&mut self) {
+        for block in self.func.blocks() {
+            if !self.sealed.contains(&block.inner) {
+                self.seal_block(block);
+            }
+        }
+    }
+
+    fn add_predecessor(
+        &mut self,
+        block: Block<'module, 'func>,
+        predecessor: Block<'module, 'func>,
+    ) {
+        assert!(
+            !self.sealed.contains(&block.inner),
+            "jump to a block which was already sealed"
+        );
+        self.predecessors
+            .entry(block.inner)
+            .or_default()
+            .push(predecessor);
+    }
+
+    fn predecessors(&self, block: Block<'module, 'func>) -> &[Block<'module, 'func>] {
+        self.predecessors
+            .get(&block.inner)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Write the value of `var` in each predecessor of `block` to `phi`, returning whatever now holds the value of `var` at the start of `block`.
+    fn add_phi_operands(
+        &mut self,
+        var: Variable,
+        phi: InstRef<'func>,
+        block: Block<'module, 'func>,
+    ) -> InstRef<'func> {
+        // A phi from before the block was sealed is the only way to get here without predecessors.
+        assert!(
+            !self.predecessors(block).is_empty(),
+            "variable used before it was defined"
+        );
+        for predecessor in self.predecessors(block).to_vec() {
+            let value = self.use_var(var, predecessor);
+            // The predecessor is already terminated, so the upsilon goes just before its terminator.
+            let upsilon = unsafe {
+                let terminator = (*(*predecessor.inner.as_ptr()).bookend).prev;
+                let upsilon =
+                    ffi::inst_upsilon(predecessor.func(), phi.inner.as_ptr(), value.inner.as_ptr());
+                predecessor.insert_inst_before(terminator, upsilon)
+            };
+            self.upsilons
+                .entry(phi.inner)
+                .or_default()
+                .push((predecessor, upsilon));
+        }
+        self.try_remove_trivial_phi(phi)
+    }
+
+    /// Replace `phi` with the one value other than itself that its upsilons write, if there is only one.
+    ///
+    /// Removing a phi can make the phis written from it trivial in turn, so those are retried as well.
+    fn try_remove_trivial_phi(&mut self, phi: InstRef<'func>) -> InstRef<'func> {
+        let mut same = None;
+        let upsilons = self.upsilons.get(&phi.inner).map_or(&[][..], Vec::as_slice);
+        for &(_, upsilon) in upsilons {
+            let (_, value) = self.upsilon_data(upsilon);
+            if value == phi || Some(value) == same {
+                continue;
+            }
+            if same.is_some() {
+                return phi;
+            }
+            same = Some(value);
+        }
+        // A phi only written from itself is in a block unreachable from any definition; leave it alone.
+        let Some(same) = same else {
+            return phi;
+        };
+
+        let users: Vec<_> = self
+            .upsilons
+            .values()
+            .flatten()
+            .map(|&(_, upsilon)| self.upsilon_data(upsilon))
+            .filter(|&(user, value)| value == phi && user != phi)
+            .map(|(user, _)| user)
+            .collect();
+        for (block, upsilon) in self.upsilons.remove(&phi.inner).unwrap() {
+            block.remove_inst(upsilon);
+        }
+        phi.replace_all_uses_with(same);
+        phi.block(self.func).remove_inst(phi);
+        self.replaced.insert(phi.inner, same);
+        for value in self.defs.values_mut() {
+            if *value == phi {
+                *value = same;
+            }
+        }
+
+        for user in users {
+            // A phi written from this one more than once, or removed while retrying an earlier user, is skipped.
+            if self.upsilons.contains_key(&user.inner) {
+                self.try_remove_trivial_phi(user);
+            }
+        }
+        // Retrying the users can remove `same` too, if it was one of them.
+        let mut same = same;
+        while let Some(&replacement) = self.replaced.get(&same.inner) {
+            same = replacement;
+        }
+        same
+    }
+
+    /// The phi an upsilon created by the builder writes to, and the value it writes.
+    fn upsilon_data(&self, upsilon: InstRef<'func>) -> (InstRef<'func>, InstRef<'func>) {
+        match upsilon.data(self.func) {
+            InstData::Upsilon { phi, value } => (phi, value),
+            _ => unreachable!("builder upsilon is not an upsilon"),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Control flow graph queries over the blocks of a function.
+
+use std::collections::HashMap;
+
+use crate::{Block, Func, raw_successors};
+
+/// The control flow graph of a [`Func`], as of when it was built with [`Cfg::new`].
+///
+/// Successors are derived from each block's terminator: a jump has one, a branch has two (or one if both targets are the same block), and a return has none.
+#[derive(Clone, Debug)]
+pub struct Cfg<'module, 'func> {
+    entry: Block<'module, 'func>,
+    blocks: Vec<Block<'module, 'func>>,
+    successors: Vec<Vec<Block<'module, 'func>>>,
+    predecessors: Vec<Vec<Block<'module, 'func>>>,
+    preorder: Vec<Block<'module, 'func>>,
+    postorder: Vec<Block<'module, 'func>>,
+    reachable: Vec<bool>,
+    index: HashMap<Block<'module, 'func>, usize>,
+}
+
+impl<'module, 'func> Cfg<'module, 'func> {
+    #[must_use]
+    pub fn new(func: Func<'module, 'func>) -> Self {
+        let entry = func.entry_block();
+        let blocks: Vec<_> = func.blocks().collect();
+        let index: HashMap<_, _> = (0..).zip(&blocks).map(|(i, &block)| (block, i)).collect();
+        let mut successors = vec![vec![]; blocks.len()];
+        let mut predecessors = vec![vec![]; blocks.len()];
+        for (i, &block) in blocks.iter().enumerate() {
+            for successor in unsafe { raw_successors(block.inner.as_ptr()) } {
+                let successor = unsafe { func.block_from_inner(successor) };
+                if successors[i].contains(&successor) {
+                    continue;
+                }
+                successors[i].push(successor);
+                predecessors[index[&successor]].push(block);
+            }
+        }
+
+        // Depth-first search from the entry block, without recursing so deep graphs don't overflow the stack.
+        let mut reachable = vec![false; blocks.len()];
+        let mut preorder = vec![];
+        let mut postorder = vec![];
+        let mut stack = vec![(index[&entry], 0)];
+        reachable[index[&entry]] = true;
+        preorder.push(entry);
+        while let Some((block, next_successor)) = stack.last_mut() {
+            let block = *block;
+            if let Some(&successor) = successors[block].get(*next_successor) {
+                *next_successor += 1;
+                let successor_index = index[&successor];
+                if !reachable[successor_index] {
+                    reachable[successor_index] = true;
+                    preorder.push(successor);
+                    stack.push((successor_index, 0));
+                }
+            } else {
+                postorder.push(blocks[block]);
+                stack.pop();
+            }
+        }
+
+        Self {
+            entry,
+            blocks,
+            successors,
+            predecessors,
+            preorder,
+            postorder,
+            reachable,
+            index,
+        }
+    }
+
+    pub fn entry_block(&self) -> Block<'module, 'func> {
+        self.entry
+    }
+
+    /// The blocks `block`'s terminator may transfer control to, in terminator order and without repeats.
+    pub fn successors(&self, block: Block<'module, 'func>) -> &[Block<'module, 'func>] {
+        &self.successors[self.index_of(block)]
+    }
+
+    /// The blocks whose terminators may transfer control to `block`, in layout order and without repeats.
+    pub fn predecessors(&self, block: Block<'module, 'func>) -> &[Block<'module, 'func>] {
+        &self.predecessors[self.index_of(block)]
+    }
+
+    /// The blocks reachable from the entry block, each before its successors in depth-first order.
+    pub fn preorder(&self) -> impl Iterator<Item = Block<'module, 'func>> {
+        self.preorder.iter().copied()
+    }
+
+    /// The blocks reachable from the entry block, each after all of its successors not on a cycle back to it.
+    pub fn postorder(&self) -> impl Iterator<Item = Block<'module, 'func>> {
+        self.postorder.iter().copied()
+    }
+
+    /// The blocks reachable from the entry block in reverse postorder, the usual order for forward data flow analyses.
+    pub fn reverse_postorder(&self) -> impl Iterator<Item = Block<'module, 'func>> {
+        self.postorder.iter().rev().copied()
+    }
+
+    pub fn is_reachable(&self, block: Block<'module, 'func>) -> bool {
+        self.reachable[self.index_of(block)]
+    }
+
+    /// The blocks which cannot be reached from the entry block, in layout order.
+    pub fn unreachable_blocks(&self) -> impl Iterator<Item = Block<'module, 'func>> {
+        self.blocks
+            .iter()
+            .copied()
+            .zip(&self.reachable)
+            .filter(|&(_, &reachable)| !reachable)
+            .map(|(block, _)| block)
+    }
+
+    fn index_of(&self, block: Block<'module, 'func>) -> usize {
+        *self
+            .index
+            .get(&block)
+            .expect("block was created after the control flow graph")
+    }
+}
@@ -3,14 +3,21 @@
 #![warn(missing_debug_implementations)]
 #![allow(clippy::new_ret_no_self)]
 
+mod builder;
+mod cfg;
 #[cfg(test)]
 mod tests;
+mod verify;
 
 use std::{
     cell::UnsafeCell,
     fmt,
+    fs::File,
+    hash::{Hash, Hasher},
+    io,
     marker::PhantomData,
     mem::MaybeUninit,
+    path::Path,
     ptr::{self, NonNull, null},
 };
 
@@ -20,7 +27,10 @@ use iron_sys as ffi;
 #[allow(unused_imports)]
 use ffi::{InstKind, InstKindGeneric, RegStatus, Regclass, SymbolKind, Trait, VReg};
 
+pub use builder::{FunctionBuilder, Variable};
+pub use cfg::Cfg;
 pub use ffi::{Arch, CallConv, SymbolBinding, System, Ty};
+pub use verify::{Diagnostics, IronError, IronErrorKind};
 
 #[derive(Clone, Copy, Debug)]
 struct InvariantOn<'brand> {
@@ -111,42 +121,82 @@ impl Drop for DataBuffer {
 #[derive(Debug)]
 pub struct Module<'module> {
     inner: NonNull<ffi::Module>,
+    arch: Arch,
+    ptr_size: u8,
     ipool: UnsafeCell<ffi::InstPool>,
     vregs: UnsafeCell<ffi::VRegBuffer>,
     // We own the memory for `Symbol` and `FuncSig` for each function
-    _func_data: UnsafeCell<Vec<(Symbol, FuncSig)>>,
+    func_data: UnsafeCell<Vec<(Symbol, FuncSig)>>,
+    // ...as well as the `FuncSig` of each indirect call
+    call_sigs: UnsafeCell<Vec<FuncSig>>,
+    // ...and the `Symbol` and contents of each data object
+    _data: UnsafeCell<Vec<(Symbol, Box<[u8]>)>>,
+    // ...and the `Symbol` and `FuncSig` of each declared function, which Iron itself never sees
+    decls: UnsafeCell<Vec<(Symbol, FuncSig, NonNull<ffi::Func>)>>,
+    // ...and the `Symbol` of each declared data object
+    data_decls: UnsafeCell<Vec<Symbol>>,
     lifetime_module: InvariantOn<'module>,
 }
 
 impl<'module> Module<'module> {
-    #[must_use]
-    fn new_owned(arch: Arch, system: System, lifetime_module: InvariantOn<'module>) -> Self {
+    fn new_owned(
+        arch: Arch,
+        system: System,
+        lifetime_module: InvariantOn<'module>,
+    ) -> Result<Self, BuildError> {
+        let Some(ptr_size) = ptr_size(arch) else {
+            return Err(BuildError::UnsupportedArch(arch));
+        };
         let inner = unsafe { nonnull(ffi::module_new(arch, system)) };
-        Self {
+        Ok(Self {
             inner,
+            arch,
+            ptr_size,
             ipool: UnsafeCell::new(ipool_new()),
             vregs: UnsafeCell::new(vrbuf_new(64)),
-            _func_data: UnsafeCell::new(vec![]),
+            func_data: UnsafeCell::new(vec![]),
+            call_sigs: UnsafeCell::new(vec![]),
+            _data: UnsafeCell::new(vec![]),
+            decls: UnsafeCell::new(vec![]),
+            data_decls: UnsafeCell::new(vec![]),
             lifetime_module,
-        }
+        })
     }
 
     pub fn new<F, R>(arch: Arch, system: System, f: F) -> R
+    where
+        F: for<'module_brand> FnOnce(Module<'module_brand>) -> R,
+    {
+        Self::try_new(arch, system, f).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like [`Module::new`], but returns an error instead of panicking if iron-rs does not support `arch`.
+    pub fn try_new<F, R>(arch: Arch, system: System, f: F) -> Result<R, BuildError>
     where
         F: for<'module_brand> FnOnce(Module<'module_brand>) -> R,
     {
         InvariantOn::new(|lifetime_module| {
-            let module = Module::new_owned(arch, system, lifetime_module);
-            f(module)
+            let module = Module::new_owned(arch, system, lifetime_module)?;
+            Ok(f(module))
         })
     }
 
     #[must_use]
     pub fn create_symbol(&self, name: impl Into<String>, binding: SymbolBinding) -> Symbol {
+        self.try_create_symbol(name, binding)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like [`Module::create_symbol`], but returns an error instead of panicking if `name` is too long.
+    pub fn try_create_symbol(
+        &self,
+        name: impl Into<String>,
+        binding: SymbolBinding,
+    ) -> Result<Symbol, BuildError> {
         let name = name.into();
         let len = name.len();
         let Ok(len) = u16::try_from(len) else {
-            panic!("symbol length ({len}) was greater than u16::MAX");
+            return Err(BuildError::SymbolTooLong { len });
         };
         let ptr = if len == 0 {
             std::ptr::null()
@@ -154,10 +204,19 @@ impl<'module> Module<'module> {
             name.as_ptr().cast()
         };
         let inner = unsafe { nonnull(ffi::symbol_new(self.inner.as_ptr(), ptr, len, binding)) };
-        Symbol { inner, _name: name }
+        Ok(Symbol { inner, name })
     }
 
     pub fn create_func<F, R>(&self, symbol: Symbol, sig: FuncSig, f: F) -> R
+    where
+        F: for<'func_brand> FnOnce(Func<'module, 'func_brand>) -> R,
+    {
+        self.try_create_func(symbol, sig, f)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like [`Module::create_func`], but returns an error instead of panicking if `symbol` has an invalid binding.
+    pub fn try_create_func<F, R>(&self, symbol: Symbol, sig: FuncSig, f: F) -> Result<R, BuildError>
     where
         F: for<'func_brand> FnOnce(Func<'module, 'func_brand>) -> R,
     {
@@ -168,7 +227,7 @@ impl<'module> Module<'module> {
             | SymbolBinding::SharedExport
             | SymbolBinding::Extern => {}
             SymbolBinding::SharedImport => {
-                panic!("function symbol cannot have binding {symbol_binding:?}");
+                return Err(BuildError::FuncBinding(symbol_binding));
             }
         }
         let inner = unsafe {
@@ -182,22 +241,56 @@ impl<'module> Module<'module> {
             ))
         };
         unsafe {
-            (*self._func_data.get()).push((symbol, sig));
+            (*self.func_data.get()).push((symbol, sig));
         }
         let func_ref = FuncRef {
             inner,
             _lifetime_module: self.lifetime_module,
         };
-        self.edit_func(func_ref, f)
+        Ok(self.edit_func(func_ref, f))
+    }
+
+    /// Declare a function defined outside this module, which can be called but has no body.
+    pub fn declare_func(&self, symbol: Symbol, sig: FuncSig) -> FuncRef<'module> {
+        let symbol_binding = unsafe { (*symbol.inner.as_ptr()).bind };
+        let arch = self.arch;
+        assert!(
+            import_directive(arch, symbol_binding).is_some(),
+            "declared function symbol cannot have binding {symbol_binding:?} on {arch:?}"
+        );
+        let inner = unsafe {
+            let inner = nonnull(ffi::func_new(
+                self.inner.as_ptr(),
+                symbol.inner.as_ptr(),
+                sig.0.as_ptr(),
+                self.ipool.get(),
+                self.vregs.get(),
+            ));
+            // Call instructions need an `FeFunc` to point to, but Iron must not codegen or emit its empty body.
+            unlink_func(self.inner.as_ptr(), inner.as_ptr());
+            inner
+        };
+        unsafe {
+            (*self.decls.get()).push((symbol, sig, inner));
+        }
+        FuncRef {
+            inner,
+            _lifetime_module: self.lifetime_module,
+        }
     }
 
     pub fn edit_func<F, R>(&self, func_ref: FuncRef<'module>, f: F) -> R
     where
         F: for<'func_brand> FnOnce(Func<'module, 'func_brand>) -> R,
     {
+        let is_decl = unsafe { &*self.decls.get() }
+            .iter()
+            .any(|&(_, _, decl)| decl == func_ref.inner);
+        assert!(!is_decl, "cannot edit the body of a declared function");
         InvariantOn::new(|lifetime_func| {
             let func = Func {
                 inner: func_ref.inner,
+                module: NonNull::from(self),
                 lifetime_func,
                 lifetime_module: self.lifetime_module,
             };
@@ -205,7 +298,179 @@ impl<'module> Module<'module> {
         })
     }
 
-    pub fn codegen(self) -> String {
+    /// Define a data object named by `symbol` containing `bytes`, aligned to `align` bytes.
+    ///
+    /// Objects in [`Section::Bss`] must be entirely zero, and are emitted as zero-initialized storage.
+    pub fn create_data(
+        &self,
+        symbol: Symbol,
+        bytes: impl Into<Box<[u8]>>,
+        align: u32,
+        section: Section,
+    ) -> DataRef<'module> {
+        let symbol_binding = unsafe { (*symbol.inner.as_ptr()).bind };
+        match symbol_binding {
+            SymbolBinding::Local | SymbolBinding::Global | SymbolBinding::SharedExport => {}
+            SymbolBinding::SharedImport | SymbolBinding::Extern => {
+                panic!("data symbol cannot have binding {symbol_binding:?}");
+            }
+        }
+        assert!(
+            align.is_power_of_two(),
+            "alignment ({align}) is not a power of two"
+        );
+        let bytes = bytes.into();
+        if section == Section::Bss {
+            assert!(
+                bytes.iter().all(|&b| b == 0),
+                "data in section Bss must be zero"
+            );
+        }
+        unsafe {
+            (*symbol.inner.as_ptr()).kind = SymbolKind::Data;
+        }
+        let inner = unsafe {
+            nonnull(ffi::data_new(
+                self.inner.as_ptr(),
+                symbol.inner.as_ptr(),
+                section.into_raw(),
+                align,
+                // NOTE: `FeData` points into these bytes rather than copying them
+                bytes.as_ptr(),
+                bytes.len(),
+            ))
+        };
+        unsafe {
+            (*self._data.get()).push((symbol, bytes));
+        }
+        DataRef {
+            inner,
+            _lifetime_module: self.lifetime_module,
+        }
+    }
+
+    /// Declare a data object defined outside this module, such as `errno`, whose address can be taken with [`Block::push_symbol_addr`].
+    pub fn declare_data(&self, symbol: Symbol) -> SymbolRef<'module> {
+        let symbol_binding = unsafe { (*symbol.inner.as_ptr()).bind };
+        let arch = self.arch;
+        assert!(
+            import_directive(arch, symbol_binding).is_some(),
+            "declared data symbol cannot have binding {symbol_binding:?} on {arch:?}"
+        );
+        let inner = symbol.inner;
+        unsafe {
+            (*inner.as_ptr()).kind = SymbolKind::Data;
+            (*self.data_decls.get()).push(symbol);
+        }
+        SymbolRef {
+            inner,
+            _lifetime_module: self.lifetime_module,
+        }
+    }
+
+    /// Make the pointer-sized word at `offset` in `data` hold the address of `target`.
+    pub fn add_reloc(
+        &self,
+        data: DataRef<'module>,
+        offset: usize,
+        target: impl Into<SymbolRef<'module>>,
+    ) {
+        let len = unsafe { (*data.inner.as_ptr()).len };
+        let end = offset.checked_add(usize::from(self.ptr_size));
+        assert!(
+            end.is_some_and(|end| end <= len),
+            "relocation at offset {offset} is out of bounds of data with length {len}"
+        );
+        unsafe {
+            ffi::data_add_reloc(data.inner.as_ptr(), offset, target.into().inner.as_ptr());
+        }
+    }
+
+    /// Keep `sig` alive for as long as this module, returning a pointer to its inner signature.
+    fn retain_call_sig(&self, sig: FuncSig) -> *mut ffi::FuncSig {
+        let inner = sig.0.as_ptr();
+        unsafe {
+            (*self.call_sigs.get()).push(sig);
+        }
+        inner
+    }
+
+    /// Check every function with [`Func::verify`], returning the problems found in each malformed one.
+    pub fn verify(&self) -> Result<(), Vec<Diagnostics>> {
+        let mut all_diagnostics = vec![];
+        let mut func = unsafe { (*self.inner.as_ptr()).funcs.first };
+        while !func.is_null() {
+            unsafe {
+                if let Err(diagnostics) = self.verify_func(func) {
+                    all_diagnostics.push(diagnostics);
+                }
+                func = (*func).list_next;
+            }
+        }
+        if all_diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(all_diagnostics)
+        }
+    }
+
+    /// # Safety
+    /// `func` must point to a valid [`ffi::Func`] of this module.
+    unsafe fn verify_func(&self, func: *mut ffi::Func) -> Result<(), Diagnostics> {
+        let errors = unsafe { verify::verify_func(func) };
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let name = self.func_name(func);
+        let errors = errors
+            .into_iter()
+            .map(|(block, kind)| IronError {
+                func: name.to_owned(),
+                block,
+                kind,
+            })
+            .collect();
+        let mut db = DataBuffer::new();
+        let ir = unsafe {
+            ffi::emit_ir_func(db.inner(), func, false);
+            db.as_str()
+        };
+        Err(Diagnostics {
+            errors,
+            ir: ir.trim().to_owned(),
+        })
+    }
+
+    /// Check every function, then generate assembly for the whole module.
+    ///
+    /// If any function is malformed, the first problem found is returned.
+    pub fn codegen(self) -> Result<String, IronError> {
+        let (mut asm, db) = self.emit()?;
+        asm.push_str(unsafe { db.as_str() }.trim());
+        Ok(asm)
+    }
+
+    /// Like [`Module::codegen`], but writes the assembly to `out` instead of returning a [`String`].
+    ///
+    /// This is not streaming: Iron still emits the whole module into one buffer in memory, which is then written to `out` as is, skipping only the copy into a [`String`] and the trimming [`Module::codegen`] does.
+    pub fn emit_asm_to(self, out: &mut impl io::Write) -> Result<(), EmitError> {
+        let (header, db) = self.emit()?;
+        out.write_all(header.as_bytes())?;
+        out.write_all(db.as_bytes())?;
+        Ok(())
+    }
+
+    /// Like [`Module::emit_asm_to`], but creates or truncates the file at `path` to write to.
+    pub fn emit_asm_to_file(self, path: impl AsRef<Path>) -> Result<(), EmitError> {
+        let mut file = File::create(path)?;
+        self.emit_asm_to(&mut file)
+    }
+
+    /// Verify and codegen every function, returning the import directives for declared functions and data and the assembly emitted by Iron.
+    fn emit(self) -> Result<(String, DataBuffer), IronError> {
+        if let Err(mut all_diagnostics) = self.verify() {
+            return Err(all_diagnostics.swap_remove(0).errors.swap_remove(0));
+        }
         let mut db = DataBuffer::new();
         let mut func = unsafe { (*self.inner.as_ptr()).funcs.first };
         while !func.is_null() {
@@ -217,8 +482,34 @@ impl<'module> Module<'module> {
         unsafe {
             ffi::emit_asm(db.inner(), self.inner.as_ptr());
         }
-        let string = unsafe { db.as_str() };
-        string.trim().to_owned()
+        let mut header = String::new();
+        let decls = unsafe { &*self.decls.get() }
+            .iter()
+            .map(|(symbol, _, _)| symbol);
+        for symbol in decls.chain(unsafe { &*self.data_decls.get() }) {
+            let binding = unsafe { (*symbol.inner.as_ptr()).bind };
+            let Some(directive) = import_directive(self.arch, binding) else {
+                unreachable!("declared symbol with binding {binding:?}");
+            };
+            if !directive.is_empty() {
+                header.push_str(directive);
+                header.push(' ');
+                header.push_str(&symbol.name);
+                header.push('\n');
+            }
+        }
+        if !header.is_empty() {
+            header.push('\n');
+        }
+        Ok((header, db))
+    }
+
+    fn func_name(&self, func: *const ffi::Func) -> &str {
+        let symbol = unsafe { (*func).sym };
+        unsafe { &*self.func_data.get() }
+            .iter()
+            .find(|(func_symbol, _)| func_symbol.inner.as_ptr() == symbol)
+            .map_or("", |(func_symbol, _)| &func_symbol.name)
     }
 }
 
@@ -226,14 +517,22 @@ impl Drop for Module<'_> {
     fn drop(&mut self) {
         let Self {
             inner,
+            arch: _,
+            ptr_size: _,
             ipool,
             vregs,
-            _func_data: _,
+            func_data: _,
+            call_sigs: _,
+            _data: _,
+            decls,
+            data_decls: _,
             lifetime_module: _,
         } = self;
         unsafe {
-            // `fe_func_destroy` is broken lmao
-            let _ = inner;
+            // `fe_func_destroy` is broken lmao, so give declared functions back for `module_destroy` to free
+            for &(_, _, decl) in decls.get_mut().iter() {
+                link_func(inner.as_ptr(), decl.as_ptr());
+            }
             ffi::module_destroy(inner.as_ptr());
             ffi::ipool_destroy(ipool.get());
             ffi::vrbuf_destroy(vregs.get());
@@ -244,7 +543,7 @@ impl Drop for Module<'_> {
 #[derive(Debug)]
 pub struct Symbol {
     inner: NonNull<ffi::Symbol>,
-    _name: String, // kept only to keep the symbol name allocation live
+    name: String, // also keeps the symbol name allocation live
 }
 
 impl Drop for Symbol {
@@ -270,6 +569,21 @@ impl FuncSig {
         params: IterParams,
         returns: IterReturns,
     ) -> Self
+    where
+        IterParams: IntoIterator<Item = FuncParam>,
+        IterParams::IntoIter: ExactSizeIterator,
+        IterReturns: IntoIterator<Item = FuncParam>,
+        IterReturns::IntoIter: ExactSizeIterator,
+    {
+        Self::try_new(call_conv, params, returns).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like [`FuncSig::new`], but returns an error instead of panicking if there are too many parameters or returns.
+    pub fn try_new<IterParams, IterReturns>(
+        call_conv: CallConv,
+        params: IterParams,
+        returns: IterReturns,
+    ) -> Result<Self, BuildError>
     where
         IterParams: IntoIterator<Item = FuncParam>,
         IterParams::IntoIter: ExactSizeIterator,
@@ -281,10 +595,10 @@ impl FuncSig {
         let param_len = params.len();
         let return_len = returns.len();
         let Ok(param_len) = u16::try_from(param_len) else {
-            panic!("number of parameters ({param_len}) was greater than u16::MAX");
+            return Err(BuildError::TooManyParams { len: param_len });
         };
         let Ok(return_len) = u16::try_from(return_len) else {
-            panic!("number of returns ({return_len}) was greater than u16::MAX");
+            return Err(BuildError::TooManyReturns { len: return_len });
         };
         let inner = unsafe { nonnull(ffi::funcsig_new(call_conv, param_len, return_len)) };
         for i in 0..param_len {
@@ -311,7 +625,7 @@ impl FuncSig {
             returns.next().is_none(),
             "`returns` violated ExactSizeIterator length"
         );
-        Self(inner)
+        Ok(Self(inner))
     }
     fn inner(&self) -> ffi::FuncSig {
         unsafe { ptr::read(self.0.as_ptr()) }
@@ -319,6 +633,25 @@ impl FuncSig {
     // TODO: should we have accessor methods for params and returns? that would mean exposing the FFI types, which may be hazardous
 }
 
+/// # Safety
+/// `sig` must point to a valid [`ffi::FuncSig`] that outlives `'a`.
+unsafe fn sig_params<'a>(sig: *const ffi::FuncSig) -> &'a [ffi::FuncParam] {
+    unsafe {
+        let params: *const ffi::FuncParam = (&raw const (*sig).params).cast();
+        std::slice::from_raw_parts(params, usize::from((*sig).param_len))
+    }
+}
+
+/// # Safety
+/// `sig` must point to a valid [`ffi::FuncSig`] that outlives `'a`.
+unsafe fn sig_returns<'a>(sig: *const ffi::FuncSig) -> &'a [ffi::FuncParam] {
+    unsafe {
+        let params: *const ffi::FuncParam = (&raw const (*sig).params).cast();
+        let param_len = usize::from((*sig).param_len);
+        std::slice::from_raw_parts(params.add(param_len), usize::from((*sig).return_len))
+    }
+}
+
 impl Drop for FuncSig {
     fn drop(&mut self) {
         let &mut Self(inner) = self;
@@ -357,27 +690,29 @@ impl Clone for FuncSig {
 #[derive(Clone, Copy, Debug)]
 pub struct Func<'module, 'func> {
     inner: NonNull<ffi::Func>,
+    // `Func` cannot escape the closure it is branded by, during which the `Module` is borrowed.
+    module: NonNull<Module<'module>>,
     lifetime_func: InvariantOn<'func>,
     lifetime_module: InvariantOn<'module>,
 }
 
 impl<'module, 'func> Func<'module, 'func> {
     pub fn entry_block(self) -> Block<'module, 'func> {
-        let inner = unsafe { nonnull((*self.inner.as_ptr()).entry_block) };
-        Block {
-            inner,
-            lifetime_func: self.lifetime_func,
-            _lifetime_module: self.lifetime_module,
-        }
+        unsafe { self.block_from_inner((*self.inner.as_ptr()).entry_block) }
     }
 
     pub fn create_block(&self) -> Block<'module, 'func> {
-        let inner = unsafe { nonnull(ffi::block_new(self.inner.as_ptr())) };
-        Block {
-            inner,
-            lifetime_func: self.lifetime_func,
-            _lifetime_module: self.lifetime_module,
-        }
+        unsafe { self.block_from_inner(ffi::block_new(self.inner.as_ptr())) }
+    }
+
+    /// Iterate over the blocks of this function in layout order, starting with the entry block.
+    ///
+    /// The blocks are collected up front, so the function may be modified during iteration. Blocks created meanwhile are not visited.
+    pub fn blocks(self) -> impl Iterator<Item = Block<'module, 'func>> {
+        let blocks: Vec<_> = unsafe { raw_blocks(self.inner.as_ptr()) }
+            .map(|inner| unsafe { self.block_from_inner(inner) })
+            .collect();
+        blocks.into_iter()
     }
 
     pub fn get_param(self, index: u16) -> InstRef<'func> {
@@ -392,12 +727,46 @@ impl<'module, 'func> Func<'module, 'func> {
         }
     }
 
+    /// Reserve `size` bytes in this function's stack frame, aligned to `align` bytes.
+    pub fn create_stack_slot(self, size: u32, align: u32) -> StackSlot<'func> {
+        assert!(
+            align.is_power_of_two(),
+            "alignment ({align}) is not a power of two"
+        );
+        let inner = unsafe {
+            let item = ffi::stack_item_new(size, align);
+            nonnull(ffi::stack_push(self.inner.as_ptr(), item))
+        };
+        StackSlot {
+            inner,
+            _lifetime_func: self.lifetime_func,
+        }
+    }
+
+    /// Check this function for malformed IR that Iron would otherwise abort or miscompile on.
+    ///
+    /// This checks that every block ends in exactly one terminator, every terminator refers to blocks of this function, instruction input and output types agree with their operations, returns match the signature, and every phi has an upsilon in each predecessor of its block.
+    pub fn verify(self) -> Result<(), Diagnostics> {
+        unsafe { self.module.as_ref().verify_func(self.inner.as_ptr()) }
+    }
+
     pub fn get_ref(self) -> FuncRef<'module> {
         FuncRef {
             inner: self.inner,
             _lifetime_module: self.lifetime_module,
         }
     }
+
+    /// # Safety
+    /// `inner` must point to a valid [`ffi::Block`] of this function.
+    unsafe fn block_from_inner(self, inner: *mut ffi::Block) -> Block<'module, 'func> {
+        Block {
+            inner: unsafe { nonnull(inner) },
+            module: self.module,
+            lifetime_func: self.lifetime_func,
+            _lifetime_module: self.lifetime_module,
+        }
+    }
 }
 
 impl fmt::Display for Func<'_, '_> {
@@ -423,25 +792,135 @@ impl<'module> From<Func<'module, '_>> for FuncRef<'module> {
     }
 }
 
+/// A data object created with [`Module::create_data`].
+#[derive(Clone, Copy, Debug)]
+pub struct DataRef<'module> {
+    inner: NonNull<ffi::Data>,
+    _lifetime_module: InvariantOn<'module>,
+}
+
+/// The symbol of a function or data object owned by a [`Module`], or of a data object declared with [`Module::declare_data`].
+#[derive(Clone, Copy, Debug)]
+pub struct SymbolRef<'module> {
+    inner: NonNull<ffi::Symbol>,
+    _lifetime_module: InvariantOn<'module>,
+}
+
+impl<'module> From<FuncRef<'module>> for SymbolRef<'module> {
+    fn from(value: FuncRef<'module>) -> Self {
+        Self {
+            inner: unsafe { nonnull((*value.inner.as_ptr()).sym) },
+            _lifetime_module: value._lifetime_module,
+        }
+    }
+}
+
+impl<'module> From<DataRef<'module>> for SymbolRef<'module> {
+    fn from(value: DataRef<'module>) -> Self {
+        Self {
+            inner: unsafe { nonnull((*value.inner.as_ptr()).sym) },
+            _lifetime_module: value._lifetime_module,
+        }
+    }
+}
+
+/// Where a data object is placed in the output.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Section {
+    /// Initialized, writable data.
+    Data,
+    /// Initialized, read-only data such as string literals.
+    Rodata,
+    /// Zero-initialized, writable storage.
+    Bss,
+}
+
+impl Section {
+    fn into_raw(self) -> ffi::Section {
+        match self {
+            Self::Data => ffi::Section::Data,
+            Self::Rodata => ffi::Section::Rodata,
+            Self::Bss => ffi::Section::Bss,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Block<'module, 'func> {
     inner: NonNull<ffi::Block>,
+    module: NonNull<Module<'module>>,
     lifetime_func: InvariantOn<'func>,
     _lifetime_module: InvariantOn<'module>,
 }
 
+impl PartialEq for Block<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl Eq for Block<'_, '_> {}
+
+impl Hash for Block<'_, '_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state);
+    }
+}
+
 impl<'module, 'func> Block<'module, 'func> {
-    pub fn push_const(self, value: Const) -> InstRef<'func> {
-        let func = self.func();
-        let ty = value.ty();
-        // TODO: make sure this 0 write is dead, e.g. doesn't affect smaller constants like I32
-        let inner = unsafe { ffi::inst_const(func, ty, 0) };
-        let value_ptr = unsafe { &raw mut (*inner).extra as *mut ffi::InstConst };
-        match value {
-            Const::U32(x) => unsafe { *value_ptr.cast() = x },
-            Const::U64(x) => unsafe { *value_ptr.cast() = x },
+    /// Iterate over the instructions of this block in order, ending with its terminator if it has one.
+    ///
+    /// The instructions are collected up front, so the block may be modified during iteration. Instructions inserted meanwhile are not visited.
+    pub fn insts(self) -> impl Iterator<Item = InstRef<'func>> {
+        let insts: Vec<_> = unsafe { raw_insts(self.inner.as_ptr()) }
+            .map(|inner| unsafe { InstRef::from_inner(inner, self.lifetime_func) })
+            .collect();
+        insts.into_iter()
+    }
+
+    /// Unlink `inst` from this block.
+    ///
+    /// `inst` must no longer be used by any other instruction, which is checked in debug builds; see [`InstRef::replace_all_uses_with`]. Using `inst` as an input afterwards panics.
+    ///
+    /// Parameters cannot be removed, since [`Func::get_param`] would keep returning them, and neither can phis that upsilons still write to.
+    pub fn remove_inst(self, inst: InstRef<'func>) {
+        assert_eq!(
+            inst.find_block(),
+            self.inner.as_ptr(),
+            "instruction is not in this block"
+        );
+        assert!(
+            !inst.is(InstKindGeneric::Param),
+            "cannot remove a parameter"
+        );
+        let inner = inst.inner.as_ptr();
+        if inst.is(InstKindGeneric::Phi) {
+            assert!(
+                !unsafe { raw_blocks(self.func()) }
+                    .flat_map(|block| unsafe { raw_insts(block) })
+                    .any(|user| unsafe {
+                        inst_is(user, InstKindGeneric::Upsilon)
+                            && (*user.cast::<ffi::Inst<ffi::InstUpsilon>>()).extra.phi == inner
+                    }),
+                "cannot remove a phi which upsilons still write to"
+            );
         }
-        unsafe { self.push_inst(inner) }
+        debug_assert!(
+            !unsafe { raw_blocks(self.func()) }
+                .flat_map(|block| unsafe { raw_insts(block) })
+                .any(|user| unsafe { raw_inputs(user).contains(&inner) }),
+            "removed instruction still has users"
+        );
+        unsafe {
+            ffi::inst_remove(inner);
+            // Detached instructions are recognized by `InstRef::find_block`.
+            (*inner).next = ptr::null_mut();
+            (*inner).prev = ptr::null_mut();
+        }
+    }
+
+    pub fn push_const(self, value: Const) -> InstRef<'func> {
+        Cursor::at_end(self).push_const(value)
     }
 
     pub fn push_binop(
@@ -450,75 +929,123 @@ impl<'module, 'func> Block<'module, 'func> {
         lhs: InstRef<'func>,
         rhs: InstRef<'func>,
     ) -> InstRef<'func> {
-        let raw_kind = InstKind(kind as u16);
-        let ty = match kind {
-            BinOp::IAdd | BinOp::ISub | BinOp::IMul => {
-                debug_assert!(raw_kind.has_trait(Trait::SAME_IN_OUT_TY));
-                lhs.ty()
-            }
-            BinOp::IEq => {
-                debug_assert!(raw_kind.has_trait(Trait::BOOL_OUT_TY));
-                Ty::Bool
-            }
-        };
-        let func = self.func();
-        unsafe {
-            let inner = ffi::inst_binop(func, ty, raw_kind, lhs.inner.as_ptr(), rhs.inner.as_ptr());
-            self.push_inst(inner)
-        }
+        Cursor::at_end(self).push_binop(kind, lhs, rhs)
     }
 
-    pub fn push_return<IterReturns>(self, returns: IterReturns)
-    where
-        IterReturns: IntoIterator<Item = InstRef<'func>>,
-        IterReturns::IntoIter: ExactSizeIterator,
-    {
-        // construct and initialize the return Inst
-        let mut returns = returns.into_iter();
-        let func = self.func();
-        let fn_return_len = unsafe { (*(*func).sig).return_len };
-        assert_eq!(
-            usize::from(fn_return_len),
-            returns.len(),
-            "incorrect number of return values"
-        );
-        let inner = unsafe { ffi::inst_return(func) };
-        for i in 0..fn_return_len {
-            let arg = returns.next().unwrap().inner.as_ptr();
-            unsafe {
-                ffi::return_set_arg(inner, i, arg);
-            }
-        }
-        assert!(
-            returns.next().is_none(),
-            "`returns` violated ExactSizeIterator length"
-        );
+    pub fn push_unop(self, kind: UnOp, value: InstRef<'func>) -> InstRef<'func> {
+        Cursor::at_end(self).push_unop(kind, value)
+    }
 
-        unsafe {
-            self.push_inst(inner);
-        }
+    /// Convert `value` to type `to`, panicking if `kind` cannot perform that conversion.
+    pub fn push_convert(self, kind: ConvOp, value: InstRef<'func>, to: Ty) -> InstRef<'func> {
+        Cursor::at_end(self).push_convert(kind, value, to)
     }
 
-    pub fn push_jump(&self, block: Self) {
-        let func = self.func();
-        unsafe {
-            let inst = ffi::inst_jump(func, block.inner.as_ptr());
-            self.push_inst(inst);
-        }
+    /// Get the address of `slot` as a [`Ty::Ptr`].
+    pub fn push_stack_addr(self, slot: StackSlot<'func>) -> InstRef<'func> {
+        Cursor::at_end(self).push_stack_addr(slot)
     }
 
-    // pub fn push_direct_call(&self, func: impl Into<FuncRef<'module>>) -> InstRef<'func> {
-    //     let func = func.into().inner.as_ptr();
-    //     let _inst = unsafe { ffi::inst_call_direct(self.func(), func) };
-    //     todo!("direct call arguments")
-    // }
+    /// Get the address of a function or data object as a [`Ty::Ptr`], including data declared with [`Module::declare_data`].
+    pub fn push_symbol_addr(self, symbol: impl Into<SymbolRef<'module>>) -> InstRef<'func> {
+        Cursor::at_end(self).push_symbol_addr(symbol)
+    }
 
-    unsafe fn push_inst(self, inner: *mut iron_sys::Inst) -> InstRef<'func> {
-        let func = self.func();
+    /// Get the address of a function, e.g. to call it with [`Block::push_indirect_call`].
+    pub fn push_func_addr(self, func: impl Into<FuncRef<'module>>) -> InstRef<'func> {
+        Cursor::at_end(self).push_func_addr(func)
+    }
+
+    /// Load a value of type `ty` from `addr`, naturally aligned.
+    pub fn push_load(self, ty: Ty, addr: InstRef<'func>) -> InstRef<'func> {
+        Cursor::at_end(self).push_load(ty, addr)
+    }
+
+    pub fn push_load_with(self, ty: Ty, addr: InstRef<'func>, flags: MemFlags) -> InstRef<'func> {
+        Cursor::at_end(self).push_load_with(ty, addr, flags)
+    }
+
+    /// Store `value` to `addr`, naturally aligned.
+    pub fn push_store(self, addr: InstRef<'func>, value: InstRef<'func>) {
+        Cursor::at_end(self).push_store(addr, value);
+    }
+
+    pub fn push_store_with(self, addr: InstRef<'func>, value: InstRef<'func>, flags: MemFlags) {
+        Cursor::at_end(self).push_store_with(addr, value, flags);
+    }
+
+    pub fn push_return<IterReturns>(self, returns: IterReturns)
+    where
+        IterReturns: IntoIterator<Item = InstRef<'func>>,
+        IterReturns::IntoIter: ExactSizeIterator,
+    {
+        Cursor::at_end(self).push_return(returns);
+    }
+
+    pub fn push_jump(&self, block: Self) {
+        Cursor::at_end(*self).push_jump(block);
+    }
+
+    pub fn push_branch(&self, cond: InstRef<'func>, then_block: Self, else_block: Self) {
+        Cursor::at_end(*self).push_branch(cond, then_block, else_block);
+    }
+
+    /// Create a phi of type `ty` after any parameters and other phis at the start of this block, taking its value from whichever upsilon last wrote to it.
+    ///
+    /// [`Func::verify`] checks that each predecessor of this block contains an upsilon for every phi.
+    pub fn push_phi(self, ty: Ty) -> InstRef<'func> {
+        let func = self.func();
+        unsafe {
+            let point = first_body_inst(self.inner.as_ptr());
+            let inner = ffi::inst_phi(func, ty);
+            self.insert_inst_before(point, inner)
+        }
+    }
+
+    /// Write `value` to `phi` when control flow leaves this block.
+    pub fn push_upsilon(self, phi: InstRef<'func>, value: InstRef<'func>) {
+        Cursor::at_end(self).push_upsilon(phi, value);
+    }
+
+    /// Call `callee` with `args`, returning one [`InstRef`] per return value of the callee.
+    pub fn push_direct_call<IterArgs>(
+        self,
+        callee: impl Into<FuncRef<'module>>,
+        args: IterArgs,
+    ) -> Vec<InstRef<'func>>
+    where
+        IterArgs: IntoIterator<Item = InstRef<'func>>,
+        IterArgs::IntoIter: ExactSizeIterator,
+    {
+        Cursor::at_end(self).push_direct_call(callee, args)
+    }
+
+    /// Call the function pointer `callee` with signature `sig`, returning one [`InstRef`] per return value.
+    pub fn push_indirect_call<IterArgs>(
+        self,
+        callee: InstRef<'func>,
+        sig: &FuncSig,
+        args: IterArgs,
+    ) -> Vec<InstRef<'func>>
+    where
+        IterArgs: IntoIterator<Item = InstRef<'func>>,
+        IterArgs::IntoIter: ExactSizeIterator,
+    {
+        Cursor::at_end(self).push_indirect_call(callee, sig, args)
+    }
+
+    /// # Safety
+    /// `point` must be an instruction in this block (or its bookend), and `inner` must not yet be in any block.
+    unsafe fn insert_inst_before(
+        self,
+        point: *mut iron_sys::Inst,
+        inner: *mut iron_sys::Inst,
+    ) -> InstRef<'func> {
+        let func = self.func();
 
         // Assert that all the instruction inputs actually come from this function. Our 'brand lifetimes should make this statically impossible, but it hardly hurts to double check.
         let inst_ref = unsafe { InstRef::from_inner(inner, self.lifetime_func) };
-        for &input in inst_ref.inputs() {
+        for &input in inst_ref.raw_inputs() {
             let source_block =
                 unsafe { InstRef::from_inner(input, self.lifetime_func) }.find_block();
             let source_func = unsafe { (*source_block).func };
@@ -528,19 +1055,441 @@ impl<'module, 'func> Block<'module, 'func> {
             );
         }
 
-        // append it to the block
         unsafe {
-            let bookend = (*self.inner.as_ptr()).bookend;
-            ffi::insert_before(bookend, inner);
+            ffi::insert_before(point, inner);
         }
         inst_ref
     }
 
+    fn module(&self) -> &Module<'module> {
+        unsafe { self.module.as_ref() }
+    }
+
     fn func(self) -> *mut iron_sys::Func {
         unsafe { (*self.inner.as_ptr()).func }
     }
 }
 
+/// A position in a block at which to insert instructions, offering the same `push_*` methods as [`Block`], which only appends.
+///
+/// Instructions pushed through a cursor are inserted in order, with the cursor staying after them.
+#[derive(Clone, Copy, Debug)]
+pub struct Cursor<'module, 'func> {
+    block: Block<'module, 'func>,
+    // Instructions are inserted before this one, which may be the bookend.
+    point: NonNull<ffi::Inst>,
+}
+
+impl<'module, 'func> Cursor<'module, 'func> {
+    /// Position a cursor at the start of `block`, after any parameters and phis.
+    pub fn at_start(block: Block<'module, 'func>) -> Self {
+        Self::before_raw(block, unsafe { first_body_inst(block.inner.as_ptr()) })
+    }
+
+    /// Position a cursor at the end of `block`, after its terminator if it has one.
+    pub fn at_end(block: Block<'module, 'func>) -> Self {
+        Self::before_raw(block, unsafe { (*block.inner.as_ptr()).bookend })
+    }
+
+    /// Position a cursor just before `inst`, panicking if `inst` is a parameter or phi, since those must stay at the start of the block.
+    pub fn before(func: Func<'module, 'func>, inst: InstRef<'func>) -> Self {
+        Self::before_raw(inst.block(func), inst.inner.as_ptr())
+    }
+
+    /// Position a cursor just after `inst`, panicking if more parameters or phis follow it.
+    pub fn after(func: Func<'module, 'func>, inst: InstRef<'func>) -> Self {
+        Self::before_raw(inst.block(func), unsafe { (*inst.inner.as_ptr()).next })
+    }
+
+    /// The block this cursor is in.
+    pub fn block(self) -> Block<'module, 'func> {
+        self.block
+    }
+
+    pub fn push_const(self, value: Const) -> InstRef<'func> {
+        let func = self.func();
+        let module = self.module();
+        let ty = value.ty(module);
+        // Integers are passed zero-extended to 64 bits. Floats are written to their own union field, over the zeroed payload.
+        let inner = unsafe { ffi::inst_const(func, ty, value.int_bits(module).unwrap_or(0)) };
+        let value_ptr = unsafe { &raw mut (*inner).extra as *mut ffi::InstConst };
+        match value {
+            Const::F16(x) => unsafe { (*value_ptr).f16 = x },
+            Const::F32(x) => unsafe { (*value_ptr).f32 = x },
+            Const::F64(x) => unsafe { (*value_ptr).f64 = x },
+            _ => {}
+        }
+        unsafe { self.push_inst(inner) }
+    }
+
+    pub fn push_binop(
+        self,
+        kind: BinOp,
+        lhs: InstRef<'func>,
+        rhs: InstRef<'func>,
+    ) -> InstRef<'func> {
+        let raw_kind = InstKind(kind as u16);
+        let (lhs_ty, rhs_ty) = (lhs.ty(), rhs.ty());
+        if raw_kind.has_trait(Trait::SAME_INPUT_TYS) {
+            assert_eq!(
+                lhs_ty, rhs_ty,
+                "{kind:?} operands have different types {lhs_ty:?} and {rhs_ty:?}"
+            );
+        }
+        if raw_kind.has_trait(Trait::INT_INPUT_TYS) {
+            assert!(
+                ty_is_int(lhs_ty) && ty_is_int(rhs_ty),
+                "{kind:?} requires integer operands but got {lhs_ty:?} and {rhs_ty:?}"
+            );
+        }
+        if raw_kind.has_trait(Trait::FLT_INPUT_TYS) {
+            assert!(
+                ty_is_float(lhs_ty) && ty_is_float(rhs_ty),
+                "{kind:?} requires float operands but got {lhs_ty:?} and {rhs_ty:?}"
+            );
+        }
+        let ty = if raw_kind.has_trait(Trait::BOOL_OUT_TY) {
+            Ty::Bool
+        } else {
+            debug_assert!(raw_kind.has_trait(Trait::SAME_IN_OUT_TY));
+            lhs_ty
+        };
+        let func = self.func();
+        unsafe {
+            let inner = ffi::inst_binop(func, ty, raw_kind, lhs.inner.as_ptr(), rhs.inner.as_ptr());
+            self.push_inst(inner)
+        }
+    }
+
+    pub fn push_unop(self, kind: UnOp, value: InstRef<'func>) -> InstRef<'func> {
+        let raw_kind = InstKind(kind as u16);
+        let value_ty = value.ty();
+        let valid_input = match kind {
+            UnOp::Neg | UnOp::Not => ty_is_int(value_ty),
+            UnOp::FNeg => ty_is_float(value_ty),
+        };
+        assert!(
+            valid_input,
+            "{kind:?} cannot be applied to type {value_ty:?}"
+        );
+        let ty = if raw_kind.has_trait(Trait::BOOL_OUT_TY) {
+            Ty::Bool
+        } else {
+            debug_assert!(raw_kind.has_trait(Trait::SAME_IN_OUT_TY));
+            value_ty
+        };
+        let func = self.func();
+        unsafe {
+            let inner = ffi::inst_unop(func, ty, raw_kind, value.inner.as_ptr());
+            self.push_inst(inner)
+        }
+    }
+
+    pub fn push_convert(self, kind: ConvOp, value: InstRef<'func>, to: Ty) -> InstRef<'func> {
+        let from = value.ty();
+        let ptr_size = self.module().ptr_size;
+        let (from_size, to_size) = (ty_size(from, ptr_size), ty_size(to, ptr_size));
+        let valid = match kind {
+            ConvOp::SignExt | ConvOp::ZeroExt => {
+                (ty_is_sized_int(from) || from == Ty::Bool)
+                    && ty_is_sized_int(to)
+                    && from_size < to_size
+            }
+            ConvOp::Trunc => ty_is_sized_int(from) && ty_is_sized_int(to) && from_size > to_size,
+            ConvOp::BitCast => from_size.is_some() && from_size == to_size,
+            ConvOp::I2F => ty_is_sized_int(from) && ty_is_float(to),
+            ConvOp::F2I => ty_is_float(from) && ty_is_sized_int(to),
+        };
+        assert!(valid, "{kind:?} cannot convert from {from:?} to {to:?}");
+        let func = self.func();
+        unsafe {
+            let inner = ffi::inst_unop(func, to, InstKind(kind as u16), value.inner.as_ptr());
+            self.push_inst(inner)
+        }
+    }
+
+    pub fn push_stack_addr(self, slot: StackSlot<'func>) -> InstRef<'func> {
+        let func = self.func();
+        unsafe {
+            let inner = ffi::inst_stack_addr(func, slot.inner.as_ptr());
+            self.push_inst(inner)
+        }
+    }
+
+    pub fn push_symbol_addr(self, symbol: impl Into<SymbolRef<'module>>) -> InstRef<'func> {
+        let func = self.func();
+        unsafe {
+            let inner = ffi::inst_sym_addr(func, symbol.into().inner.as_ptr());
+            self.push_inst(inner)
+        }
+    }
+
+    pub fn push_func_addr(self, func: impl Into<FuncRef<'module>>) -> InstRef<'func> {
+        self.push_symbol_addr(func.into())
+    }
+
+    pub fn push_load(self, ty: Ty, addr: InstRef<'func>) -> InstRef<'func> {
+        self.push_load_with(ty, addr, MemFlags::default())
+    }
+
+    pub fn push_load_with(self, ty: Ty, addr: InstRef<'func>, flags: MemFlags) -> InstRef<'func> {
+        let addr_ty = addr.ty();
+        assert_eq!(
+            addr_ty,
+            Ty::Ptr,
+            "load address has type {addr_ty:?} but must be a pointer"
+        );
+        let align = flags.align_for(ty, self.module().ptr_size);
+        let func = self.func();
+        unsafe {
+            let inner = ffi::inst_load(func, ty, addr.inner.as_ptr(), align, NO_OFFSET);
+            self.push_inst(inner)
+        }
+    }
+
+    pub fn push_store(self, addr: InstRef<'func>, value: InstRef<'func>) {
+        self.push_store_with(addr, value, MemFlags::default());
+    }
+
+    pub fn push_store_with(self, addr: InstRef<'func>, value: InstRef<'func>, flags: MemFlags) {
+        let addr_ty = addr.ty();
+        assert_eq!(
+            addr_ty,
+            Ty::Ptr,
+            "store address has type {addr_ty:?} but must be a pointer"
+        );
+        let align = flags.align_for(value.ty(), self.module().ptr_size);
+        let func = self.func();
+        unsafe {
+            let inner = ffi::inst_store(
+                func,
+                addr.inner.as_ptr(),
+                value.inner.as_ptr(),
+                align,
+                NO_OFFSET,
+            );
+            self.push_inst(inner);
+        }
+    }
+
+    pub fn push_return<IterReturns>(self, returns: IterReturns)
+    where
+        IterReturns: IntoIterator<Item = InstRef<'func>>,
+        IterReturns::IntoIter: ExactSizeIterator,
+    {
+        // construct and initialize the return Inst
+        let mut returns = returns.into_iter();
+        let func = self.func();
+        let fn_return_len = unsafe { (*(*func).sig).return_len };
+        assert_eq!(
+            usize::from(fn_return_len),
+            returns.len(),
+            "incorrect number of return values"
+        );
+        let inner = unsafe { ffi::inst_return(func) };
+        for i in 0..fn_return_len {
+            let arg = returns.next().unwrap().inner.as_ptr();
+            unsafe {
+                ffi::return_set_arg(inner, i, arg);
+            }
+        }
+        assert!(
+            returns.next().is_none(),
+            "`returns` violated ExactSizeIterator length"
+        );
+
+        unsafe {
+            self.push_inst(inner);
+        }
+    }
+
+    pub fn push_jump(self, block: Block<'module, 'func>) {
+        let func = self.func();
+        unsafe {
+            let inst = ffi::inst_jump(func, block.inner.as_ptr());
+            self.push_inst(inst);
+        }
+    }
+
+    pub fn push_branch(
+        self,
+        cond: InstRef<'func>,
+        then_block: Block<'module, 'func>,
+        else_block: Block<'module, 'func>,
+    ) {
+        let cond_ty = cond.ty();
+        assert!(
+            ty_is_int(cond_ty),
+            "branch condition has type {cond_ty:?} but must be a boolean or integer"
+        );
+        let func = self.func();
+        unsafe {
+            let inst = ffi::inst_branch(
+                func,
+                cond.inner.as_ptr(),
+                then_block.inner.as_ptr(),
+                else_block.inner.as_ptr(),
+            );
+            self.push_inst(inst);
+        }
+    }
+
+    /// Like [`Block::push_phi`], this always inserts after any parameters and other phis at the start of the block, regardless of the cursor.
+    pub fn push_phi(self, ty: Ty) -> InstRef<'func> {
+        self.block.push_phi(ty)
+    }
+
+    pub fn push_upsilon(self, phi: InstRef<'func>, value: InstRef<'func>) {
+        assert!(
+            phi.is(InstKindGeneric::Phi),
+            "upsilon destination is not a phi"
+        );
+        assert_eq!(
+            phi.ty(),
+            value.ty(),
+            "upsilon value has type {:?} but the phi has type {:?}",
+            value.ty(),
+            phi.ty()
+        );
+        let func = self.func();
+        unsafe {
+            let inner = ffi::inst_upsilon(func, phi.inner.as_ptr(), value.inner.as_ptr());
+            self.push_inst(inner);
+        }
+    }
+
+    pub fn push_direct_call<IterArgs>(
+        self,
+        callee: impl Into<FuncRef<'module>>,
+        args: IterArgs,
+    ) -> Vec<InstRef<'func>>
+    where
+        IterArgs: IntoIterator<Item = InstRef<'func>>,
+        IterArgs::IntoIter: ExactSizeIterator,
+    {
+        let callee = callee.into().inner.as_ptr();
+        unsafe {
+            let sig = (*callee).sig;
+            let inner = ffi::inst_call_direct(self.func(), callee);
+            self.push_call(inner, sig, args)
+        }
+    }
+
+    pub fn push_indirect_call<IterArgs>(
+        self,
+        callee: InstRef<'func>,
+        sig: &FuncSig,
+        args: IterArgs,
+    ) -> Vec<InstRef<'func>>
+    where
+        IterArgs: IntoIterator<Item = InstRef<'func>>,
+        IterArgs::IntoIter: ExactSizeIterator,
+    {
+        let callee_ty = callee.ty();
+        assert_eq!(
+            callee_ty,
+            Ty::Ptr,
+            "indirect call target has type {callee_ty:?} but must be a pointer"
+        );
+        // `FeInstCall` points to its signature, so it must live as long as the module
+        let sig = self.module().retain_call_sig(sig.clone());
+        unsafe {
+            let inner = ffi::inst_call_indirect(self.func(), callee.inner.as_ptr(), sig);
+            self.push_call(inner, sig, args)
+        }
+    }
+
+    /// # Safety
+    /// `inner` must be a freshly created call instruction of this block's function, and `sig` must be the signature it calls through.
+    unsafe fn push_call<IterArgs>(
+        self,
+        inner: *mut ffi::Inst,
+        sig: *const ffi::FuncSig,
+        args: IterArgs,
+    ) -> Vec<InstRef<'func>>
+    where
+        IterArgs: IntoIterator<Item = InstRef<'func>>,
+        IterArgs::IntoIter: ExactSizeIterator,
+    {
+        let mut args = args.into_iter();
+        let params = unsafe { sig_params(sig) };
+        assert_eq!(
+            params.len(),
+            args.len(),
+            "incorrect number of call arguments"
+        );
+        for (i, param) in (0..).zip(params) {
+            let arg = args.next().unwrap();
+            assert_eq!(
+                arg.ty(),
+                param.ty,
+                "call argument {i} has type {:?} but the parameter has type {:?}",
+                arg.ty(),
+                param.ty
+            );
+            unsafe {
+                ffi::call_set_arg(inner, i, arg.inner.as_ptr());
+            }
+        }
+        assert!(
+            args.next().is_none(),
+            "`args` violated ExactSizeIterator length"
+        );
+        let call = unsafe { self.push_inst(inner) };
+
+        // A single return value is the call itself, otherwise each one is projected out of the call's tuple.
+        let returns = unsafe { sig_returns(sig) };
+        if returns.len() == 1 {
+            return vec![call];
+        }
+        (0..returns.len())
+            .map(|i| unsafe {
+                let proj = ffi::inst_proj(self.func(), inner, i);
+                self.push_inst(proj)
+            })
+            .collect()
+    }
+
+    unsafe fn push_inst(self, inner: *mut iron_sys::Inst) -> InstRef<'func> {
+        let point = self.point.as_ptr();
+        unsafe {
+            assert!(
+                !(*point).next.is_null(),
+                "cursor position was removed from its block"
+            );
+            self.block.insert_inst_before(point, inner)
+        }
+    }
+
+    fn module(&self) -> &Module<'module> {
+        self.block.module()
+    }
+
+    fn func(self) -> *mut iron_sys::Func {
+        self.block.func()
+    }
+
+    fn before_raw(block: Block<'module, 'func>, point: *mut ffi::Inst) -> Self {
+        assert!(
+            !unsafe {
+                inst_is(point, InstKindGeneric::Param) || inst_is(point, InstKindGeneric::Phi)
+            },
+            "cannot insert before a parameter or phi"
+        );
+        Self {
+            block,
+            point: unsafe { nonnull(point) },
+        }
+    }
+}
+
+/// A region of a function's stack frame, created with [`Func::create_stack_slot`].
+#[derive(Clone, Copy, Debug)]
+pub struct StackSlot<'func> {
+    inner: NonNull<ffi::StackItem>,
+    _lifetime_func: InvariantOn<'func>,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct InstRef<'func> {
     inner: NonNull<ffi::Inst>,
@@ -548,6 +1497,14 @@ pub struct InstRef<'func> {
     // lifetime_module: InvariantOn<'module>,
 }
 
+impl PartialEq for InstRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl Eq for InstRef<'_> {}
+
 impl<'func> InstRef<'func> {
     unsafe fn from_inner(inner: *mut ffi::Inst, lifetime_func: InvariantOn<'func>) -> Self {
         unsafe {
@@ -557,37 +1514,444 @@ impl<'func> InstRef<'func> {
             }
         }
     }
-    fn inputs(self) -> &'func [*mut ffi::Inst] {
-        let mut input_len = usize::MAX;
-        let input_start = unsafe {
-            // it's *probably* fine to pass a null pointer for target :3
-            ffi::inst_list_inputs(null(), self.inner.as_ptr(), &raw mut input_len)
-        };
-        assert_ne!(
-            input_len,
-            usize::MAX,
-            "uninitialized out parameter `input_len`"
-        );
-        if input_start.is_null() {
-            debug_assert_eq!(input_len, 0, "non-zero length slice to null pointer");
-            &[]
-        } else {
-            unsafe { std::slice::from_raw_parts(input_start, input_len) }
-        }
+    pub fn kind(self) -> Opcode {
+        let kind = unsafe { (*self.inner.as_ptr()).kind };
+        Opcode::from_raw(kind).expect("instruction has an architecture-specific kind")
+    }
+    /// The values this instruction uses, in operand order.
+    pub fn inputs(self) -> impl Iterator<Item = Self> {
+        let lifetime_func = self._lifetime_func;
+        self.raw_inputs()
+            .iter()
+            .map(move |&input| unsafe { Self::from_inner(input, lifetime_func) })
+    }
+    fn raw_inputs(self) -> &'func [*mut ffi::Inst] {
+        unsafe { raw_inputs(self.inner.as_ptr()) }
     }
     pub fn ty(self) -> Ty {
         unsafe { (*self.inner.as_ptr()).ty }
     }
+    /// The block containing this instruction.
+    pub fn block<'module>(self, func: Func<'module, 'func>) -> Block<'module, 'func> {
+        unsafe { func.block_from_inner(self.find_block()) }
+    }
+    /// Make every instruction using this one use `other` instead.
+    pub fn replace_all_uses_with(self, other: Self) {
+        assert_eq!(
+            self.ty(),
+            other.ty(),
+            "replacement has type {:?} but the replaced instruction has type {:?}",
+            other.ty(),
+            self.ty()
+        );
+        unsafe {
+            let func = (*self.find_block()).func;
+            debug_assert_eq!(
+                (*other.find_block()).func,
+                func,
+                "replacement from a different function"
+            );
+            ffi::replace_uses(func, self.inner.as_ptr(), other.inner.as_ptr());
+        }
+    }
+    fn is(self, kind: InstKindGeneric) -> bool {
+        unsafe { inst_is(self.inner.as_ptr(), kind) }
+    }
+    /// Decode the operands and other payload of this instruction.
+    pub fn data<'module>(self, func: Func<'module, 'func>) -> InstData<'module, 'func> {
+        let lifetime_func = self._lifetime_func;
+        let inst = |inner| unsafe { Self::from_inner(inner, lifetime_func) };
+        let insts = |args: *mut *mut ffi::Inst, len: u16| -> Vec<Self> {
+            if len == 0 {
+                return vec![];
+            }
+            unsafe { std::slice::from_raw_parts(args, usize::from(len)) }
+                .iter()
+                .map(|&arg| inst(arg))
+                .collect()
+        };
+        let block = |inner| unsafe { func.block_from_inner(inner) };
+        let opcode = self.kind();
+        if let Some(op) = BinOp::from_opcode(opcode) {
+            let ffi::InstBinop { lhs, rhs } = unsafe { self.extra() };
+            return InstData::Binop {
+                op,
+                lhs: inst(lhs),
+                rhs: inst(rhs),
+            };
+        }
+        if let Some(op) = UnOp::from_opcode(opcode) {
+            let ffi::InstUnop { un } = unsafe { self.extra() };
+            return InstData::Unop {
+                op,
+                value: inst(un),
+            };
+        }
+        if let Some(op) = ConvOp::from_opcode(opcode) {
+            let ffi::InstUnop { un } = unsafe { self.extra() };
+            return InstData::Convert {
+                op,
+                value: inst(un),
+            };
+        }
+        match opcode {
+            Opcode::Param => {
+                let ffi::InstParam { index } = unsafe { self.extra() };
+                InstData::Param { index }
+            }
+            Opcode::Proj => {
+                let ffi::InstProj { idx } = unsafe { self.extra() };
+                InstData::Proj {
+                    tuple: inst(self.raw_inputs()[0]),
+                    index: idx,
+                }
+            }
+            Opcode::Const => InstData::Const(Const::from_raw(self.ty(), unsafe { self.extra() })),
+            Opcode::SymAddr => {
+                let ffi::InstSymAddr { sym } = unsafe { self.extra() };
+                InstData::SymAddr(SymbolRef {
+                    inner: unsafe { nonnull(sym) },
+                    _lifetime_module: func.lifetime_module,
+                })
+            }
+            Opcode::StackAddr => {
+                let ffi::InstStackAddr { item } = unsafe { self.extra() };
+                InstData::StackAddr(StackSlot {
+                    inner: unsafe { nonnull(item) },
+                    _lifetime_func: lifetime_func,
+                })
+            }
+            Opcode::Mov => {
+                let ffi::InstUnop { un } = unsafe { self.extra() };
+                InstData::Mov { value: inst(un) }
+            }
+            Opcode::Upsilon => {
+                let ffi::InstUpsilon { val, phi } = unsafe { self.extra() };
+                InstData::Upsilon {
+                    phi: inst(phi),
+                    value: inst(val),
+                }
+            }
+            Opcode::Phi => InstData::Phi,
+            Opcode::Load => {
+                let ffi::InstLoad { ptr, align, .. } = unsafe { self.extra() };
+                InstData::Load {
+                    addr: inst(ptr),
+                    flags: MemFlags { align: Some(align) },
+                }
+            }
+            Opcode::Store => {
+                let ffi::InstStore {
+                    ptr, val, align, ..
+                } = unsafe { self.extra() };
+                InstData::Store {
+                    addr: inst(ptr),
+                    value: inst(val),
+                    flags: MemFlags { align: Some(align) },
+                }
+            }
+            Opcode::Jump => {
+                let ffi::InstJump { to } = unsafe { self.extra() };
+                InstData::Jump { to: block(to) }
+            }
+            Opcode::Branch => {
+                let ffi::InstBranch {
+                    cond,
+                    if_true,
+                    if_false,
+                } = unsafe { self.extra() };
+                InstData::Branch {
+                    cond: inst(cond),
+                    then_block: block(if_true),
+                    else_block: block(if_false),
+                }
+            }
+            Opcode::Return => {
+                let ffi::InstReturn { args, len, .. } = unsafe { self.extra() };
+                InstData::Return {
+                    values: insts(args, len),
+                }
+            }
+            Opcode::CallDirect => {
+                let ffi::InstCallDirect {
+                    callee, args, len, ..
+                } = unsafe { self.extra() };
+                InstData::CallDirect {
+                    callee: FuncRef {
+                        inner: unsafe { nonnull(callee) },
+                        _lifetime_module: func.lifetime_module,
+                    },
+                    args: insts(args, len),
+                }
+            }
+            Opcode::CallIndirect => {
+                let ffi::InstCall {
+                    callee, args, len, ..
+                } = unsafe { self.extra() };
+                InstData::CallIndirect {
+                    callee: inst(callee),
+                    args: insts(args, len),
+                }
+            }
+            _ => unreachable!("{opcode:?} has no decoding"),
+        }
+    }
+    /// # Safety
+    /// `T` must be the payload type of this instruction's kind.
+    unsafe fn extra<T: Copy>(self) -> T {
+        unsafe { (*self.inner.as_ptr().cast::<ffi::Inst<T>>()).extra }
+    }
     fn find_block(self) -> *mut ffi::Block {
         let mut inst: *const ffi::Inst = self.inner.as_ptr();
         while unsafe { (*inst).kind } != ffi::InstKind::from(ffi::InstKindGeneric::Bookend) {
             inst = unsafe { (*inst).next };
+            assert!(!inst.is_null(), "instruction was removed from its block");
         }
         let bookend: *const ffi::Inst<ffi::InstBookend> = inst.cast();
         unsafe { (*bookend).extra.block }
     }
 }
 
+/// The operands and other payload of an instruction, as decoded by [`InstRef::data`].
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub enum InstData<'module, 'func> {
+    Param {
+        index: u16,
+    },
+    /// One value of a call returning several.
+    Proj {
+        tuple: InstRef<'func>,
+        index: usize,
+    },
+    /// Integers are decoded as the unsigned variant of their width, since Iron does not record signedness.
+    Const(Const),
+    SymAddr(SymbolRef<'module>),
+    StackAddr(StackSlot<'func>),
+    Binop {
+        op: BinOp,
+        lhs: InstRef<'func>,
+        rhs: InstRef<'func>,
+    },
+    Unop {
+        op: UnOp,
+        value: InstRef<'func>,
+    },
+    Convert {
+        op: ConvOp,
+        value: InstRef<'func>,
+    },
+    Mov {
+        value: InstRef<'func>,
+    },
+    Upsilon {
+        phi: InstRef<'func>,
+        value: InstRef<'func>,
+    },
+    Phi,
+    /// The alignment in `flags` is always `Some`.
+    Load {
+        addr: InstRef<'func>,
+        flags: MemFlags,
+    },
+    /// The alignment in `flags` is always `Some`.
+    Store {
+        addr: InstRef<'func>,
+        value: InstRef<'func>,
+        flags: MemFlags,
+    },
+    Jump {
+        to: Block<'module, 'func>,
+    },
+    Branch {
+        cond: InstRef<'func>,
+        then_block: Block<'module, 'func>,
+        else_block: Block<'module, 'func>,
+    },
+    Return {
+        values: Vec<InstRef<'func>>,
+    },
+    CallDirect {
+        callee: FuncRef<'module>,
+        args: Vec<InstRef<'func>>,
+    },
+    CallIndirect {
+        callee: InstRef<'func>,
+        args: Vec<InstRef<'func>>,
+    },
+}
+
+/// Remove `func` from the function list of `module`.
+///
+/// # Safety
+/// `func` must point to a valid [`ffi::Func`] in the function list of `module`.
+unsafe fn unlink_func(module: *mut ffi::Module, func: *mut ffi::Func) {
+    unsafe {
+        let (prev, next) = ((*func).list_prev, (*func).list_next);
+        if prev.is_null() {
+            (*module).funcs.first = next;
+        } else {
+            (*prev).list_next = next;
+        }
+        if next.is_null() {
+            (*module).funcs.last = prev;
+        } else {
+            (*next).list_prev = prev;
+        }
+        (*func).list_prev = ptr::null_mut();
+        (*func).list_next = ptr::null_mut();
+    }
+}
+
+/// Append `func` to the function list of `module`.
+///
+/// # Safety
+/// `func` must point to a valid [`ffi::Func`] of `module` which is in no function list.
+unsafe fn link_func(module: *mut ffi::Module, func: *mut ffi::Func) {
+    unsafe {
+        let last = (*module).funcs.last;
+        (*func).list_prev = last;
+        (*func).list_next = ptr::null_mut();
+        if last.is_null() {
+            (*module).funcs.first = func;
+        } else {
+            (*last).list_next = func;
+        }
+        (*module).funcs.last = func;
+    }
+}
+
+/// The assembler directive importing a function declared with `binding`, which is empty if none is needed, or `None` if such a function cannot be declared on `arch`.
+///
+/// The GNU assembler treats every undefined symbol as external, so x86-64 needs no directive. XR/17032 has no support for shared library imports here.
+// NOTE: `.extern` follows the XR/17032 toolchain's assembly syntax, but has not been checked against its assembler.
+fn import_directive(arch: Arch, binding: SymbolBinding) -> Option<&'static str> {
+    match (arch, binding) {
+        (Arch::X86_64, SymbolBinding::Extern | SymbolBinding::SharedImport) => Some(""),
+        (Arch::Xr17032, SymbolBinding::Extern) => Some(".extern"),
+        _ => None,
+    }
+}
+
+/// # Safety
+/// `inst` must point to a valid [`ffi::Inst`] whose inputs outlive `'a`.
+unsafe fn raw_inputs<'a>(inst: *mut ffi::Inst) -> &'a [*mut ffi::Inst] {
+    let mut input_len = usize::MAX;
+    let input_start = unsafe {
+        // it's *probably* fine to pass a null pointer for target :3
+        ffi::inst_list_inputs(null(), inst, &raw mut input_len)
+    };
+    assert_ne!(
+        input_len,
+        usize::MAX,
+        "uninitialized out parameter `input_len`"
+    );
+    if input_start.is_null() {
+        debug_assert_eq!(input_len, 0, "non-zero length slice to null pointer");
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(input_start, input_len) }
+    }
+}
+
+/// # Safety
+/// `inst` must point to a valid [`ffi::Inst`].
+unsafe fn inst_is(inst: *const ffi::Inst, kind: InstKindGeneric) -> bool {
+    unsafe { (*inst).kind == InstKind::from(kind) }
+}
+
+/// Iterate over the blocks of `func` in layout order.
+///
+/// # Safety
+/// `func` must point to a valid [`ffi::Func`] which is not modified during iteration.
+unsafe fn raw_blocks(func: *const ffi::Func) -> impl Iterator<Item = *mut ffi::Block> {
+    let first = unsafe { (*func).entry_block };
+    std::iter::successors(NonNull::new(first), |block| unsafe {
+        NonNull::new((*block.as_ptr()).list_next)
+    })
+    .map(NonNull::as_ptr)
+}
+
+/// Iterate over the instructions of `block` in order, excluding the bookend.
+///
+/// # Safety
+/// `block` must point to a valid [`ffi::Block`] which is not modified during iteration.
+unsafe fn raw_insts(block: *const ffi::Block) -> impl Iterator<Item = *mut ffi::Inst> {
+    let bookend = unsafe { (*block).bookend };
+    let first = unsafe { (*bookend).next };
+    std::iter::successors(Some(first), |&inst| Some(unsafe { (*inst).next }))
+        .take_while(move |&inst| inst != bookend)
+}
+
+/// The first instruction of `block` which is not a parameter or phi, or its bookend if there is none.
+///
+/// # Safety
+/// `block` must point to a valid [`ffi::Block`].
+unsafe fn first_body_inst(block: *const ffi::Block) -> *mut ffi::Inst {
+    unsafe {
+        raw_insts(block)
+            .find(|&inst| {
+                !(inst_is(inst, InstKindGeneric::Param) || inst_is(inst, InstKindGeneric::Phi))
+            })
+            .unwrap_or((*block).bookend)
+    }
+}
+
+/// The blocks `block`'s terminator may transfer control to, in order.
+///
+/// # Safety
+/// `block` must point to a valid [`ffi::Block`].
+unsafe fn raw_successors(block: *const ffi::Block) -> Vec<*mut ffi::Block> {
+    unsafe {
+        let bookend = (*block).bookend;
+        let last = (*bookend).prev;
+        if inst_is(last, InstKindGeneric::Jump) {
+            let jump: *const ffi::Inst<ffi::InstJump> = last.cast();
+            vec![(*jump).extra.to]
+        } else if inst_is(last, InstKindGeneric::Branch) {
+            let branch: *const ffi::Inst<ffi::InstBranch> = last.cast();
+            vec![(*branch).extra.if_true, (*branch).extra.if_false]
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Whether `ty` is accepted by instructions taking integer inputs, such as integer arithmetic, comparisons and branches.
+const fn ty_is_int(ty: Ty) -> bool {
+    matches!(ty, Ty::Bool | Ty::Ptr) || ty_is_sized_int(ty)
+}
+
+/// Whether `ty` is an integer of a fixed width, as required by conversions.
+const fn ty_is_sized_int(ty: Ty) -> bool {
+    matches!(ty, Ty::I8 | Ty::I16 | Ty::I32 | Ty::I64)
+}
+
+const fn ty_is_float(ty: Ty) -> bool {
+    matches!(ty, Ty::F16 | Ty::F32 | Ty::F64)
+}
+
+/// The size of a scalar type in bytes, or `None` for non-scalar types.
+fn ty_size(ty: Ty, ptr_size: u8) -> Option<u8> {
+    match ty {
+        Ty::Bool | Ty::I8 => Some(1),
+        Ty::I16 | Ty::F16 => Some(2),
+        Ty::I32 | Ty::F32 => Some(4),
+        Ty::I64 | Ty::F64 => Some(8),
+        Ty::Ptr => Some(ptr_size),
+        _ => None,
+    }
+}
+
+/// The size of a pointer on `arch`, or `None` if iron-rs does not support `arch`.
+fn ptr_size(arch: Arch) -> Option<u8> {
+    match arch {
+        Arch::X86_64 => Some(8),
+        Arch::Xr17032 => Some(4),
+        _ => None,
+    }
+}
+
 // An unfortunate function that only exists because of a lack of const traits and not being able to infallibly convert an enum into an integer without wrapping or truncating.
 const fn inst_kind_to_u8(kind: InstKindGeneric) -> u8 {
     // Use of transmute is simple and assures the same size as u16.
@@ -598,19 +1962,198 @@ const fn inst_kind_to_u8(kind: InstKindGeneric) -> u8 {
     v as u8
 }
 
+/// An error from a fallible constructor such as [`Module::try_create_symbol`].
 #[non_exhaustive]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BuildError {
+    /// A symbol name was longer than [`u16::MAX`] bytes.
+    SymbolTooLong { len: usize },
+    /// A function signature had more than [`u16::MAX`] parameters.
+    TooManyParams { len: usize },
+    /// A function signature had more than [`u16::MAX`] returns.
+    TooManyReturns { len: usize },
+    /// A function was defined with a symbol binding that cannot have a body.
+    FuncBinding(SymbolBinding),
+    /// A module was created for an architecture iron-rs does not support.
+    UnsupportedArch(Arch),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SymbolTooLong { len } => {
+                write!(f, "symbol length ({len}) was greater than u16::MAX")
+            }
+            Self::TooManyParams { len } => {
+                write!(f, "number of parameters ({len}) was greater than u16::MAX")
+            }
+            Self::TooManyReturns { len } => {
+                write!(f, "number of returns ({len}) was greater than u16::MAX")
+            }
+            Self::FuncBinding(binding) => {
+                write!(f, "function symbol cannot have binding {binding:?}")
+            }
+            Self::UnsupportedArch(arch) => write!(f, "unsupported architecture {arch:?}"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// An error from writing assembly with [`Module::emit_asm_to`].
+#[derive(Debug)]
+pub enum EmitError {
+    Iron(IronError),
+    Io(io::Error),
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Iron(err) => err.fmt(f),
+            Self::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for EmitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Iron(err) => Some(err),
+            Self::Io(err) => Some(err),
+        }
+    }
+}
+
+impl From<IronError> for EmitError {
+    fn from(value: IronError) -> Self {
+        Self::Iron(value)
+    }
+}
+
+impl From<io::Error> for EmitError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// The constant byte offset Iron adds to the address of a load or store, which iron-rs does not expose.
+const NO_OFFSET: u16 = 0;
+
+/// Options for [`Block::push_load_with`] and [`Block::push_store_with`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct MemFlags {
+    /// The alignment of the address in bytes, or the natural alignment of the accessed type if `None`.
+    pub align: Option<u16>,
+}
+
+impl MemFlags {
+    fn align_for(self, ty: Ty, ptr_size: u8) -> u16 {
+        let Some(size) = ty_size(ty, ptr_size) else {
+            panic!("cannot access memory of type {ty:?}");
+        };
+        let align = self.align.unwrap_or(u16::from(size));
+        assert!(
+            align.is_power_of_two(),
+            "alignment ({align}) is not a power of two"
+        );
+        align
+    }
+}
+
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Const {
+    Bool(bool),
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
     U32(u32),
+    I32(i32),
     U64(u64),
+    I64(i64),
+    /// An integer as wide as a pointer on the module's [`Arch`].
+    USize(u64),
+    /// An integer as wide as a pointer on the module's [`Arch`].
+    ISize(i64),
+    /// A raw address of type [`Ty::Ptr`].
+    Ptr(u64),
+    /// The bits of an IEEE 754 binary16 float.
+    F16(u16),
+    F32(f32),
+    F64(f64),
 }
 impl Const {
-    fn ty(self) -> Ty {
+    fn from_raw(ty: Ty, value: ffi::InstConst) -> Self {
+        // Integers were zero-extended to 64 bits when pushed, so truncating recovers them.
+        unsafe {
+            match ty {
+                Ty::Bool => Self::Bool(value.val != 0),
+                Ty::I8 => Self::U8(value.val as u8),
+                Ty::I16 => Self::U16(value.val as u16),
+                Ty::I32 => Self::U32(value.val as u32),
+                Ty::I64 => Self::U64(value.val),
+                Ty::Ptr => Self::Ptr(value.val),
+                Ty::F16 => Self::F16(value.f16),
+                Ty::F32 => Self::F32(value.f32),
+                Ty::F64 => Self::F64(value.f64),
+                _ => unreachable!("constant of type {ty:?}"),
+            }
+        }
+    }
+
+    fn ty(self, module: &Module) -> Ty {
         match self {
-            Self::U32(_) => Ty::I32,
-            Self::U64(_) => Ty::I64,
+            Self::Bool(_) => Ty::Bool,
+            Self::U8(_) | Self::I8(_) => Ty::I8,
+            Self::U16(_) | Self::I16(_) => Ty::I16,
+            Self::U32(_) | Self::I32(_) => Ty::I32,
+            Self::U64(_) | Self::I64(_) => Ty::I64,
+            Self::USize(_) | Self::ISize(_) => match module.ptr_size {
+                4 => Ty::I32,
+                8 => Ty::I64,
+                size => unreachable!("pointer size {size}"),
+            },
+            Self::Ptr(_) => Ty::Ptr,
+            Self::F16(_) => Ty::F16,
+            Self::F32(_) => Ty::F32,
+            Self::F64(_) => Ty::F64,
         }
     }
+    /// The bits of an integer constant, zero-extended to 64 bits.
+    fn int_bits(self, module: &Module) -> Option<u64> {
+        let ptr_bits = u32::from(module.ptr_size) * 8;
+        let check_ptr_sized = |fits: bool| {
+            let arch = module.arch;
+            assert!(
+                fits,
+                "constant {self:?} does not fit in {ptr_bits} bits on {arch:?}"
+            );
+        };
+        let bits = match self {
+            Self::Bool(x) => u64::from(x),
+            Self::U8(x) => u64::from(x),
+            Self::I8(x) => u64::from(x as u8),
+            Self::U16(x) => u64::from(x),
+            Self::I16(x) => u64::from(x as u16),
+            Self::U32(x) => u64::from(x),
+            Self::I32(x) => u64::from(x as u32),
+            Self::U64(x) => x,
+            Self::I64(x) => x as u64,
+            Self::USize(x) | Self::Ptr(x) => {
+                check_ptr_sized(ptr_bits == 64 || x >> ptr_bits == 0);
+                x
+            }
+            Self::ISize(x) => {
+                let min = -1i64 << (ptr_bits - 1);
+                check_ptr_sized(ptr_bits == 64 || (min..=!min).contains(&x));
+                x as u64 & (u64::MAX >> (64 - ptr_bits))
+            }
+            Self::F16(_) | Self::F32(_) | Self::F64(_) => return None,
+        };
+        Some(bits)
+    }
 }
 
 #[repr(u8)]
@@ -620,5 +2163,367 @@ pub enum BinOp {
     IAdd = inst_kind_to_u8(InstKindGeneric::IAdd),
     ISub = inst_kind_to_u8(InstKindGeneric::ISub),
     IMul = inst_kind_to_u8(InstKindGeneric::IMul),
+    IDiv = inst_kind_to_u8(InstKindGeneric::IDiv),
+    UDiv = inst_kind_to_u8(InstKindGeneric::UDiv),
+    IRem = inst_kind_to_u8(InstKindGeneric::IRem),
+    URem = inst_kind_to_u8(InstKindGeneric::URem),
+    And = inst_kind_to_u8(InstKindGeneric::And),
+    Or = inst_kind_to_u8(InstKindGeneric::Or),
+    Xor = inst_kind_to_u8(InstKindGeneric::Xor),
+    Shl = inst_kind_to_u8(InstKindGeneric::Shl),
+    /// Logical (unsigned) shift right.
+    USr = inst_kind_to_u8(InstKindGeneric::USr),
+    /// Arithmetic (signed) shift right.
+    ISr = inst_kind_to_u8(InstKindGeneric::ISr),
+    ILt = inst_kind_to_u8(InstKindGeneric::ILt),
+    ULt = inst_kind_to_u8(InstKindGeneric::ULt),
+    ILe = inst_kind_to_u8(InstKindGeneric::ILe),
+    ULe = inst_kind_to_u8(InstKindGeneric::ULe),
+    IGt = inst_kind_to_u8(InstKindGeneric::IGt),
+    UGt = inst_kind_to_u8(InstKindGeneric::UGt),
+    IGe = inst_kind_to_u8(InstKindGeneric::IGe),
+    UGe = inst_kind_to_u8(InstKindGeneric::UGe),
     IEq = inst_kind_to_u8(InstKindGeneric::IEq),
+    INe = inst_kind_to_u8(InstKindGeneric::INe),
+    FAdd = inst_kind_to_u8(InstKindGeneric::FAdd),
+    FSub = inst_kind_to_u8(InstKindGeneric::FSub),
+    FMul = inst_kind_to_u8(InstKindGeneric::FMul),
+    FDiv = inst_kind_to_u8(InstKindGeneric::FDiv),
+    FRem = inst_kind_to_u8(InstKindGeneric::FRem),
+    FLt = inst_kind_to_u8(InstKindGeneric::FLt),
+    FLe = inst_kind_to_u8(InstKindGeneric::FLe),
+    FGt = inst_kind_to_u8(InstKindGeneric::FGt),
+    FGe = inst_kind_to_u8(InstKindGeneric::FGe),
+    FEq = inst_kind_to_u8(InstKindGeneric::FEq),
+    FNe = inst_kind_to_u8(InstKindGeneric::FNe),
+}
+
+impl BinOp {
+    fn from_opcode(opcode: Opcode) -> Option<Self> {
+        match opcode {
+            Opcode::IAdd => Some(Self::IAdd),
+            Opcode::ISub => Some(Self::ISub),
+            Opcode::IMul => Some(Self::IMul),
+            Opcode::IDiv => Some(Self::IDiv),
+            Opcode::UDiv => Some(Self::UDiv),
+            Opcode::IRem => Some(Self::IRem),
+            Opcode::URem => Some(Self::URem),
+            Opcode::And => Some(Self::And),
+            Opcode::Or => Some(Self::Or),
+            Opcode::Xor => Some(Self::Xor),
+            Opcode::Shl => Some(Self::Shl),
+            Opcode::USr => Some(Self::USr),
+            Opcode::ISr => Some(Self::ISr),
+            Opcode::ILt => Some(Self::ILt),
+            Opcode::ULt => Some(Self::ULt),
+            Opcode::ILe => Some(Self::ILe),
+            Opcode::ULe => Some(Self::ULe),
+            Opcode::IGt => Some(Self::IGt),
+            Opcode::UGt => Some(Self::UGt),
+            Opcode::IGe => Some(Self::IGe),
+            Opcode::UGe => Some(Self::UGe),
+            Opcode::IEq => Some(Self::IEq),
+            Opcode::INe => Some(Self::INe),
+            Opcode::FAdd => Some(Self::FAdd),
+            Opcode::FSub => Some(Self::FSub),
+            Opcode::FMul => Some(Self::FMul),
+            Opcode::FDiv => Some(Self::FDiv),
+            Opcode::FRem => Some(Self::FRem),
+            Opcode::FLt => Some(Self::FLt),
+            Opcode::FLe => Some(Self::FLe),
+            Opcode::FGt => Some(Self::FGt),
+            Opcode::FGe => Some(Self::FGe),
+            Opcode::FEq => Some(Self::FEq),
+            Opcode::FNe => Some(Self::FNe),
+            Opcode::Param
+            | Opcode::Proj
+            | Opcode::Const
+            | Opcode::SymAddr
+            | Opcode::StackAddr
+            | Opcode::Mov
+            | Opcode::Upsilon
+            | Opcode::Not
+            | Opcode::Neg
+            | Opcode::FNeg
+            | Opcode::Trunc
+            | Opcode::SignExt
+            | Opcode::ZeroExt
+            | Opcode::BitCast
+            | Opcode::I2F
+            | Opcode::F2I
+            | Opcode::Load
+            | Opcode::LoadVolatile
+            | Opcode::Store
+            | Opcode::StoreVolatile
+            | Opcode::Phi
+            | Opcode::Branch
+            | Opcode::Jump
+            | Opcode::Return
+            | Opcode::CallDirect
+            | Opcode::CallIndirect => None,
+        }
+    }
+}
+
+#[repr(u8)]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug)]
+pub enum UnOp {
+    /// Two's complement integer negation.
+    Neg = inst_kind_to_u8(InstKindGeneric::Neg),
+    /// Bitwise not for integers, logical not for [`Ty::Bool`].
+    Not = inst_kind_to_u8(InstKindGeneric::Not),
+    FNeg = inst_kind_to_u8(InstKindGeneric::FNeg),
+}
+
+impl UnOp {
+    fn from_opcode(opcode: Opcode) -> Option<Self> {
+        match opcode {
+            Opcode::Neg => Some(Self::Neg),
+            Opcode::Not => Some(Self::Not),
+            Opcode::FNeg => Some(Self::FNeg),
+            Opcode::Param
+            | Opcode::Proj
+            | Opcode::Const
+            | Opcode::SymAddr
+            | Opcode::StackAddr
+            | Opcode::IAdd
+            | Opcode::ISub
+            | Opcode::IMul
+            | Opcode::IDiv
+            | Opcode::UDiv
+            | Opcode::IRem
+            | Opcode::URem
+            | Opcode::And
+            | Opcode::Or
+            | Opcode::Xor
+            | Opcode::Shl
+            | Opcode::USr
+            | Opcode::ISr
+            | Opcode::ILt
+            | Opcode::ULt
+            | Opcode::ILe
+            | Opcode::ULe
+            | Opcode::IGt
+            | Opcode::UGt
+            | Opcode::IGe
+            | Opcode::UGe
+            | Opcode::IEq
+            | Opcode::INe
+            | Opcode::FAdd
+            | Opcode::FSub
+            | Opcode::FMul
+            | Opcode::FDiv
+            | Opcode::FRem
+            | Opcode::FLt
+            | Opcode::FLe
+            | Opcode::FGt
+            | Opcode::FGe
+            | Opcode::FEq
+            | Opcode::FNe
+            | Opcode::Mov
+            | Opcode::Upsilon
+            | Opcode::Trunc
+            | Opcode::SignExt
+            | Opcode::ZeroExt
+            | Opcode::BitCast
+            | Opcode::I2F
+            | Opcode::F2I
+            | Opcode::Load
+            | Opcode::LoadVolatile
+            | Opcode::Store
+            | Opcode::StoreVolatile
+            | Opcode::Phi
+            | Opcode::Branch
+            | Opcode::Jump
+            | Opcode::Return
+            | Opcode::CallDirect
+            | Opcode::CallIndirect => None,
+        }
+    }
+}
+
+#[repr(u8)]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug)]
+pub enum ConvOp {
+    /// Widen an integer, filling the new high bits with its sign bit.
+    SignExt = inst_kind_to_u8(InstKindGeneric::SignExt),
+    /// Widen an integer or [`Ty::Bool`], filling the new high bits with zeroes.
+    ZeroExt = inst_kind_to_u8(InstKindGeneric::ZeroExt),
+    /// Narrow an integer, discarding its high bits.
+    Trunc = inst_kind_to_u8(InstKindGeneric::Trunc),
+    /// Reinterpret the bits of a value as another type of the same size.
+    BitCast = inst_kind_to_u8(InstKindGeneric::BitCast),
+    /// Convert a signed integer to the nearest float.
+    I2F = inst_kind_to_u8(InstKindGeneric::I2F),
+    /// Convert a float to a signed integer, rounding towards zero.
+    F2I = inst_kind_to_u8(InstKindGeneric::F2I),
+}
+
+impl ConvOp {
+    fn from_opcode(opcode: Opcode) -> Option<Self> {
+        match opcode {
+            Opcode::SignExt => Some(Self::SignExt),
+            Opcode::ZeroExt => Some(Self::ZeroExt),
+            Opcode::Trunc => Some(Self::Trunc),
+            Opcode::BitCast => Some(Self::BitCast),
+            Opcode::I2F => Some(Self::I2F),
+            Opcode::F2I => Some(Self::F2I),
+            Opcode::Param
+            | Opcode::Proj
+            | Opcode::Const
+            | Opcode::SymAddr
+            | Opcode::StackAddr
+            | Opcode::IAdd
+            | Opcode::ISub
+            | Opcode::IMul
+            | Opcode::IDiv
+            | Opcode::UDiv
+            | Opcode::IRem
+            | Opcode::URem
+            | Opcode::And
+            | Opcode::Or
+            | Opcode::Xor
+            | Opcode::Shl
+            | Opcode::USr
+            | Opcode::ISr
+            | Opcode::ILt
+            | Opcode::ULt
+            | Opcode::ILe
+            | Opcode::ULe
+            | Opcode::IGt
+            | Opcode::UGt
+            | Opcode::IGe
+            | Opcode::UGe
+            | Opcode::IEq
+            | Opcode::INe
+            | Opcode::FAdd
+            | Opcode::FSub
+            | Opcode::FMul
+            | Opcode::FDiv
+            | Opcode::FRem
+            | Opcode::FLt
+            | Opcode::FLe
+            | Opcode::FGt
+            | Opcode::FGe
+            | Opcode::FEq
+            | Opcode::FNe
+            | Opcode::Mov
+            | Opcode::Upsilon
+            | Opcode::Not
+            | Opcode::Neg
+            | Opcode::FNeg
+            | Opcode::Load
+            | Opcode::LoadVolatile
+            | Opcode::Store
+            | Opcode::StoreVolatile
+            | Opcode::Phi
+            | Opcode::Branch
+            | Opcode::Jump
+            | Opcode::Return
+            | Opcode::CallDirect
+            | Opcode::CallIndirect => None,
+        }
+    }
+}
+
+/// Define [`Opcode`] and its conversion from Iron's instruction kinds from one list, so neither can miss a variant.
+macro_rules! opcodes {
+    ($($(#[doc = $doc:literal])* $name:ident,)*) => {
+        /// The architecture-independent kind of an instruction, as returned by [`InstRef::kind`].
+        #[repr(u8)]
+        #[non_exhaustive]
+        #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+        pub enum Opcode {
+            $($(#[doc = $doc])* $name = inst_kind_to_u8(InstKindGeneric::$name),)*
+        }
+
+        impl Opcode {
+            // Named constants are the only way to match on the discriminants.
+            #[allow(non_upper_case_globals)]
+            fn from_raw(kind: InstKind) -> Option<Self> {
+                $(const $name: u16 = Opcode::$name as u16;)*
+                match kind.0 {
+                    $($name => Some(Self::$name),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    /// A function parameter, created by [`Func::get_param`].
+    Param,
+    /// One value of a call returning several, created by [`Block::push_direct_call`] and [`Block::push_indirect_call`].
+    Proj,
+    Const,
+    SymAddr,
+    StackAddr,
+    IAdd,
+    ISub,
+    IMul,
+    IDiv,
+    UDiv,
+    IRem,
+    URem,
+    And,
+    Or,
+    Xor,
+    Shl,
+    USr,
+    ISr,
+    ILt,
+    ULt,
+    ILe,
+    ULe,
+    IGt,
+    UGt,
+    IGe,
+    UGe,
+    IEq,
+    INe,
+    FAdd,
+    FSub,
+    FMul,
+    FDiv,
+    FRem,
+    FLt,
+    FLe,
+    FGt,
+    FGe,
+    FEq,
+    FNe,
+    /// A copy of a value.
+    Mov,
+    /// A write to a phi, created by [`Block::push_upsilon`].
+    Upsilon,
+    Not,
+    Neg,
+    FNeg,
+    Trunc,
+    SignExt,
+    ZeroExt,
+    BitCast,
+    I2F,
+    F2I,
+    Load,
+    LoadVolatile,
+    Store,
+    StoreVolatile,
+    Phi,
+    Branch,
+    Jump,
+    Return,
+    CallDirect,
+    CallIndirect,
+}
+
+impl Opcode {
+    /// Whether instructions of this kind end a block.
+    #[must_use]
+    pub fn is_terminator(self) -> bool {
+        InstKind(u16::from(self as u8)).has_trait(Trait::TERMINATOR)
+    }
 }
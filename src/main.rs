@@ -16,7 +16,7 @@ fn main() {
             let sub = entry.push_binop(BinOp::ISub, add1, add2);
             entry.push_return([sub]);
         });
-        module.codegen()
+        module.codegen().unwrap()
     });
     println!("{code}");
     assert_eq!(
@@ -17,7 +17,7 @@ fn its_alive() {
             entry.push_return([param]);
             println!("{func}");
         });
-        module.codegen()
+        module.codegen().unwrap()
     });
     println!("{code}");
     assert_eq!(
@@ -42,7 +42,7 @@ fn binop_const_test() {
             let sub = entry.push_binop(BinOp::ISub, add1, add2);
             entry.push_return([sub]);
         });
-        module.codegen()
+        module.codegen().unwrap()
     });
     println!("{code}");
     assert_eq!(
@@ -58,18 +58,40 @@ fn cmp_branch_test() {
         let func_sig = FuncSig::new(CallConv::Jackal, [], [FuncParam { ty: Ty::I32 }]);
         module.create_func(func_symbol, func_sig, |func| {
             let entry = func.entry_block();
+            let then_block = func.create_block();
+            let else_block = func.create_block();
             let const2 = entry.push_const(Const::U32(2));
             let const5 = entry.push_const(Const::U32(5));
             let add = entry.push_binop(BinOp::IAdd, const2, const2);
             let cmp = entry.push_binop(BinOp::IEq, add, const5);
-            entry.push_return([cmp]);
+            entry.push_branch(cmp, then_block, else_block);
+            let one = then_block.push_const(Const::U32(1));
+            then_block.push_return([one]);
+            let zero = else_block.push_const(Const::U32(0));
+            else_block.push_return([zero]);
+
+            let InstData::Branch {
+                cond,
+                then_block: branch_then,
+                else_block: branch_else,
+            } = entry.insts().last().unwrap().data(func)
+            else {
+                panic!("expected a branch");
+            };
+            assert_eq!(cond, cmp);
+            assert_eq!(branch_then, then_block);
+            assert_eq!(branch_else, else_block);
         });
-        module.codegen()
+        module.codegen().unwrap()
     });
     println!("{code}");
+    assert!(code.starts_with(".section text\n\ncmp_branch_test:\n.global cmp_branch_test\n.b1:\n"));
+    let lines: Vec<_> = code.lines().collect();
+    assert!(lines.contains(&".b2:"));
+    assert!(lines.contains(&".b3:"));
     assert_eq!(
-        code,
-        ".section text\n\ncmp_branch_test:\n.global cmp_branch_test\n.b1:\n    addi t0, zero, 2\n    addi t1, zero, 5\n    addi t0, t0, 2\n    sub  t0, t0, t1\n    slti t0, t0, 1\n    mov  a3, t0\n    ret"
+        lines.iter().filter(|&&line| line.trim() == "ret").count(),
+        2
     );
 }
 
@@ -82,7 +104,7 @@ fn infinite_loop() {
             let entry = func.entry_block();
             entry.push_jump(entry);
         });
-        module.codegen()
+        module.codegen().unwrap()
     });
     assert_eq!(
         code,
@@ -102,7 +124,7 @@ fn infinite_loop2() {
             b2.push_jump(b1);
             //panic!("{func}");
         });
-        module.codegen()
+        module.codegen().unwrap()
     });
     assert_eq!(
         code,
@@ -157,3 +179,1477 @@ fn incorrect_number_of_return_values() {
         });
     });
 }
+
+/// Assert that `code` contains the global function `name`.
+///
+/// Block labels are not checked, since only single-function modules have shown how Iron numbers them.
+fn assert_global_func(code: &str, name: &str) {
+    assert!(
+        code.contains(&format!("\n{name}:\n.global {name}\n")),
+        "missing global function {name}"
+    );
+}
+
+/// The number of lines of `code` which are exactly `inst` once indentation is removed.
+fn count_insts(code: &str, inst: &str) -> usize {
+    code.lines().filter(|line| line.trim() == inst).count()
+}
+
+#[test]
+fn direct_call() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let add_symbol = module.create_symbol("add", SymbolBinding::Global);
+        let add_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }, FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        let add = module.create_func(add_symbol, add_sig, |func| {
+            let entry = func.entry_block();
+            let sum = entry.push_binop(BinOp::IAdd, func.get_param(0), func.get_param(1));
+            entry.push_return([sum]);
+            func.get_ref()
+        });
+        let main_symbol = module.create_symbol("main", SymbolBinding::Global);
+        let main_sig = FuncSig::new(CallConv::Jackal, [], [FuncParam { ty: Ty::I32 }]);
+        module.create_func(main_symbol, main_sig, |func| {
+            let entry = func.entry_block();
+            let const1 = entry.push_const(Const::U32(1));
+            let const2 = entry.push_const(Const::U32(2));
+            let returns = entry.push_direct_call(add, [const1, const2]);
+            assert_eq!(returns.len(), 1);
+            assert_eq!(returns[0].ty(), Ty::I32);
+            entry.push_return(returns.iter().copied());
+
+            let call = entry
+                .insts()
+                .find(|inst| inst.kind() == Opcode::CallDirect)
+                .unwrap();
+            let InstData::CallDirect { args, .. } = call.data(func) else {
+                panic!("expected a direct call");
+            };
+            assert_eq!(args, [const1, const2]);
+            let InstData::Return { values } = entry.insts().last().unwrap().data(func) else {
+                panic!("expected a return");
+            };
+            assert_eq!(values, returns);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "add");
+    assert_global_func(&code, "main");
+    assert_eq!(count_insts(&code, "ret"), 2);
+}
+
+#[test]
+#[should_panic(expected = "incorrect number of call arguments")]
+fn direct_call_wrong_arity() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let callee_symbol = module.create_symbol("callee", SymbolBinding::Global);
+        let callee_sig = FuncSig::new(CallConv::Jackal, [FuncParam { ty: Ty::I32 }], []);
+        let callee = module.create_func(callee_symbol, callee_sig, |func| {
+            func.entry_block().push_return([]);
+            func.get_ref()
+        });
+        let caller_symbol = module.create_symbol("caller", SymbolBinding::Global);
+        let caller_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(caller_symbol, caller_sig, |func| {
+            func.entry_block().push_direct_call(callee, []);
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "indirect call target has type I32 but must be a pointer")]
+fn indirect_call_non_pointer() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("bad_indirect", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [FuncParam { ty: Ty::I32 }], []);
+        let callee_sig = func_sig.clone();
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            entry.push_indirect_call(func.get_param(0), &callee_sig, []);
+        });
+    });
+}
+
+#[test]
+fn indirect_call() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("apply", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::Ptr }, FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        let callee_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let returns =
+                entry.push_indirect_call(func.get_param(0), &callee_sig, [func.get_param(1)]);
+            assert_eq!(returns.len(), 1);
+            assert_eq!(returns[0].ty(), Ty::I32);
+            entry.push_return(returns);
+
+            let call = entry
+                .insts()
+                .find(|inst| inst.kind() == Opcode::CallIndirect)
+                .unwrap();
+            let InstData::CallIndirect { callee, args } = call.data(func) else {
+                panic!("expected an indirect call");
+            };
+            assert_eq!(callee, func.get_param(0));
+            assert_eq!(args, [func.get_param(1)]);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "apply");
+    assert_eq!(count_insts(&code, "ret"), 1);
+}
+
+#[test]
+fn branch_test() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("branch_test", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let then_block = func.create_block();
+            let else_block = func.create_block();
+            let const5 = entry.push_const(Const::U32(5));
+            let cmp = entry.push_binop(BinOp::IEq, func.get_param(0), const5);
+            entry.push_branch(cmp, then_block, else_block);
+            let const1 = then_block.push_const(Const::U32(1));
+            then_block.push_return([const1]);
+            let const0 = else_block.push_const(Const::U32(0));
+            else_block.push_return([const0]);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "branch_test");
+    assert_eq!(count_insts(&code, "ret"), 2);
+}
+
+#[test]
+#[should_panic(expected = "branch condition has type F32 but must be a boolean or integer")]
+fn branch_on_float() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("branch_on_float", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [FuncParam { ty: Ty::F32 }], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            entry.push_branch(func.get_param(0), entry, entry);
+        });
+    });
+}
+
+#[test]
+fn phi_test() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("phi_test", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::Bool }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let then_block = func.create_block();
+            let else_block = func.create_block();
+            let merge = func.create_block();
+            entry.push_branch(func.get_param(0), then_block, else_block);
+            let phi = merge.push_phi(Ty::I32);
+            let const1 = then_block.push_const(Const::U32(1));
+            then_block.push_upsilon(phi, const1);
+            then_block.push_jump(merge);
+            let const2 = else_block.push_const(Const::U32(2));
+            else_block.push_upsilon(phi, const2);
+            else_block.push_jump(merge);
+            merge.push_return([phi]);
+
+            for (block, value) in [(then_block, const1), (else_block, const2)] {
+                let insts: Vec<_> = block.insts().collect();
+                assert_eq!(insts.len(), 3);
+                let InstData::Upsilon {
+                    phi: upsilon_phi,
+                    value: upsilon_value,
+                } = insts[1].data(func)
+                else {
+                    panic!("expected an upsilon");
+                };
+                assert_eq!((upsilon_phi, upsilon_value), (phi, value));
+            }
+            assert_eq!(merge.insts().next(), Some(phi));
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "phi_test");
+    for label in [".b2:", ".b3:", ".b4:"] {
+        assert_eq!(count_insts(&code, label), 1, "missing {label}");
+    }
+    assert_eq!(count_insts(&code, "ret"), 1);
+}
+
+#[test]
+fn phi_missing_upsilon() {
+    let err = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("phi_missing_upsilon", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::Bool }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let then_block = func.create_block();
+            let merge = func.create_block();
+            entry.push_branch(func.get_param(0), then_block, merge);
+            let phi = merge.push_phi(Ty::I32);
+            let const1 = then_block.push_const(Const::U32(1));
+            then_block.push_upsilon(phi, const1);
+            then_block.push_jump(merge);
+            merge.push_return([phi]);
+        });
+        module.codegen().unwrap_err()
+    });
+    assert_eq!(err.block, 2);
+    assert_eq!(err.kind, IronErrorKind::MissingUpsilon { predecessor: 0 });
+}
+
+#[test]
+fn function_builder_loop() {
+    // Count a parameter down to zero, returning the number of iterations.
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("count_down", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let mut builder = FunctionBuilder::new(func);
+            let n = builder.declare_var(Ty::I32);
+            let count = builder.declare_var(Ty::I32);
+            let entry = func.entry_block();
+            let header = func.create_block();
+            let body = func.create_block();
+            let exit = func.create_block();
+
+            builder.seal_block(entry);
+            builder.def_var(n, entry, func.get_param(0));
+            let zero = entry.push_const(Const::U32(0));
+            builder.def_var(count, entry, zero);
+            builder.push_jump(entry, header);
+
+            let n_header = builder.use_var(n, header);
+            assert_eq!(n_header.kind(), Opcode::Phi);
+            let zero = header.push_const(Const::U32(0));
+            let done = header.push_binop(BinOp::IEq, n_header, zero);
+            builder.push_branch(header, done, exit, body);
+
+            builder.seal_block(body);
+            let one = body.push_const(Const::U32(1));
+            let n_body = builder.use_var(n, body);
+            let n_next = body.push_binop(BinOp::ISub, n_body, one);
+            builder.def_var(n, body, n_next);
+            let count_body = builder.use_var(count, body);
+            let count_next = body.push_binop(BinOp::IAdd, count_body, one);
+            builder.def_var(count, body, count_next);
+            builder.push_jump(body, header);
+            builder.seal_block(header);
+
+            builder.seal_block(exit);
+            let count_exit = builder.use_var(count, exit);
+            assert_eq!(count_exit.kind(), Opcode::Phi);
+            exit.push_return([count_exit]);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "count_down");
+    for label in [".b2:", ".b3:", ".b4:"] {
+        assert_eq!(count_insts(&code, label), 1, "missing {label}");
+    }
+    assert_eq!(count_insts(&code, "ret"), 1);
+}
+
+#[test]
+fn function_builder_trivial_phi() {
+    // A loop which never redefines its variable shouldn't need a phi for it.
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("trivial_phi", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let mut builder = FunctionBuilder::new(func);
+            let x = builder.declare_var(Ty::I32);
+            let entry = func.entry_block();
+            let header = func.create_block();
+            let body = func.create_block();
+            let exit = func.create_block();
+
+            builder.seal_block(entry);
+            let param = func.get_param(0);
+            builder.def_var(x, entry, param);
+            builder.push_jump(entry, header);
+
+            let x_header = builder.use_var(x, header);
+            assert_eq!(x_header.kind(), Opcode::Phi);
+            let sum = header.push_binop(BinOp::IAdd, x_header, x_header);
+            let zero = header.push_const(Const::U32(0));
+            let done = header.push_binop(BinOp::IEq, sum, zero);
+            builder.push_branch(header, done, exit, body);
+
+            builder.seal_block(body);
+            builder.push_jump(body, header);
+            builder.seal_block(header);
+
+            assert!(header.insts().all(|inst| inst.kind() != Opcode::Phi));
+            assert!(body.insts().all(|inst| inst.kind() != Opcode::Upsilon));
+            assert_eq!(sum.inputs().collect::<Vec<_>>(), [param, param]);
+            builder.seal_block(exit);
+            assert_eq!(builder.use_var(x, exit), param);
+            exit.push_return([sum]);
+            func.verify().unwrap();
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "jump to a block which was already sealed")]
+fn function_builder_jump_to_sealed() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("jump_to_sealed", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let mut builder = FunctionBuilder::new(func);
+            let entry = func.entry_block();
+            let exit = func.create_block();
+            builder.seal_block(exit);
+            builder.push_jump(entry, exit);
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "variable used before it was defined")]
+fn function_builder_undefined_var_before_seal() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("undefined_var_before_seal", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], [FuncParam { ty: Ty::I32 }]);
+        module.create_func(func_symbol, func_sig, |func| {
+            let mut builder = FunctionBuilder::new(func);
+            let x = builder.declare_var(Ty::I32);
+            let entry = func.entry_block();
+            let value = builder.use_var(x, entry);
+            entry.push_return([value]);
+            builder.seal_block(entry);
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "variable used before it was defined")]
+fn function_builder_undefined_var() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("undefined_var", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], [FuncParam { ty: Ty::I32 }]);
+        module.create_func(func_symbol, func_sig, |func| {
+            let mut builder = FunctionBuilder::new(func);
+            let x = builder.declare_var(Ty::I32);
+            builder.seal_all_blocks();
+            builder.use_var(x, func.entry_block());
+        });
+    });
+}
+
+#[test]
+fn binop_result_types() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("binop_result_types", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I64 }, FuncParam { ty: Ty::I64 }],
+            [],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let (lhs, rhs) = (func.get_param(0), func.get_param(1));
+            for kind in [
+                BinOp::IDiv,
+                BinOp::URem,
+                BinOp::And,
+                BinOp::Xor,
+                BinOp::Shl,
+                BinOp::ISr,
+            ] {
+                assert_eq!(entry.push_binop(kind, lhs, rhs).ty(), Ty::I64, "{kind:?}");
+            }
+            for kind in [BinOp::ILt, BinOp::UGe, BinOp::INe] {
+                assert_eq!(entry.push_binop(kind, lhs, rhs).ty(), Ty::Bool, "{kind:?}");
+            }
+            entry.push_return([]);
+        });
+    });
+}
+
+#[test]
+fn binop_pointer_and_bool() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("binop_pointer_and_bool", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::Ptr }, FuncParam { ty: Ty::Bool }],
+            [],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let (ptr, flag) = (func.get_param(0), func.get_param(1));
+            assert_eq!(entry.push_binop(BinOp::IAdd, ptr, ptr).ty(), Ty::Ptr);
+            assert_eq!(entry.push_binop(BinOp::ULt, ptr, ptr).ty(), Ty::Bool);
+            assert_eq!(entry.push_binop(BinOp::IEq, ptr, ptr).ty(), Ty::Bool);
+            for kind in [BinOp::And, BinOp::Or, BinOp::Xor] {
+                assert_eq!(
+                    entry.push_binop(kind, flag, flag).ty(),
+                    Ty::Bool,
+                    "{kind:?}"
+                );
+            }
+            assert_eq!(entry.push_unop(UnOp::Not, flag).ty(), Ty::Bool);
+            entry.push_branch(ptr, entry, entry);
+            assert!(func.verify().is_ok());
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "IAdd operands have different types I32 and I64")]
+fn binop_mismatched_types() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("binop_mismatched_types", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let lhs = entry.push_const(Const::U32(1));
+            let rhs = entry.push_const(Const::U64(2));
+            entry.push_binop(BinOp::IAdd, lhs, rhs);
+        });
+    });
+}
+
+#[test]
+fn unop_test() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("unop_test", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }, FuncParam { ty: Ty::Bool }],
+            [FuncParam { ty: Ty::I32 }, FuncParam { ty: Ty::Bool }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let neg = entry.push_unop(UnOp::Neg, func.get_param(0));
+            let not = entry.push_unop(UnOp::Not, neg);
+            let bool_not = entry.push_unop(UnOp::Not, func.get_param(1));
+            assert_eq!(not.ty(), Ty::I32);
+            assert_eq!(bool_not.ty(), Ty::Bool);
+            entry.push_return([not, bool_not]);
+
+            let InstData::Unop {
+                op: UnOp::Neg,
+                value,
+            } = neg.data(func)
+            else {
+                panic!("expected a negation");
+            };
+            assert_eq!(value, func.get_param(0));
+            let InstData::Unop {
+                op: UnOp::Not,
+                value,
+            } = not.data(func)
+            else {
+                panic!("expected a bitwise not");
+            };
+            assert_eq!(value, neg);
+            let InstData::Unop {
+                op: UnOp::Not,
+                value,
+            } = bool_not.data(func)
+            else {
+                panic!("expected a boolean not");
+            };
+            assert_eq!(value, func.get_param(1));
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "unop_test");
+    assert_eq!(count_insts(&code, "ret"), 1);
+}
+
+#[test]
+#[should_panic(expected = "FNeg cannot be applied to type I32")]
+fn unop_wrong_type() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("unop_wrong_type", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [FuncParam { ty: Ty::I32 }], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            func.entry_block().push_unop(UnOp::FNeg, func.get_param(0));
+        });
+    });
+}
+
+#[test]
+fn convert_test() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("convert_test", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I16 }, FuncParam { ty: Ty::I64 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let wide = entry.push_convert(ConvOp::SignExt, func.get_param(0), Ty::I32);
+            let narrow = entry.push_convert(ConvOp::Trunc, func.get_param(1), Ty::I32);
+            let ptr = entry.push_convert(ConvOp::BitCast, narrow, Ty::Ptr);
+            assert_eq!(ptr.ty(), Ty::Ptr);
+            let sum = entry.push_binop(BinOp::IAdd, wide, narrow);
+            entry.push_return([sum]);
+
+            let expected = [
+                (wide, func.get_param(0), Ty::I32),
+                (narrow, func.get_param(1), Ty::I32),
+                (ptr, narrow, Ty::Ptr),
+            ];
+            for (inst, input, ty) in expected {
+                let InstData::Convert { value, .. } = inst.data(func) else {
+                    panic!("expected a conversion");
+                };
+                assert_eq!((value, inst.ty()), (input, ty));
+            }
+            assert!(matches!(
+                wide.data(func),
+                InstData::Convert {
+                    op: ConvOp::SignExt,
+                    ..
+                }
+            ));
+            assert!(matches!(
+                narrow.data(func),
+                InstData::Convert {
+                    op: ConvOp::Trunc,
+                    ..
+                }
+            ));
+            assert!(matches!(
+                ptr.data(func),
+                InstData::Convert {
+                    op: ConvOp::BitCast,
+                    ..
+                }
+            ));
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "convert_test");
+    assert_eq!(count_insts(&code, "ret"), 1);
+}
+
+#[test]
+#[should_panic(expected = "ZeroExt cannot convert from I64 to I32")]
+fn convert_narrowing_extend() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("convert_narrowing_extend", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [FuncParam { ty: Ty::I64 }], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            func.entry_block()
+                .push_convert(ConvOp::ZeroExt, func.get_param(0), Ty::I32);
+        });
+    });
+}
+
+#[test]
+fn const_types() {
+    // Integers come back as the unsigned variant of their width, since that is all the IR records.
+    let cases = [
+        (Const::Bool(true), Ty::Bool, Const::Bool(true)),
+        (Const::U8(255), Ty::I8, Const::U8(255)),
+        (Const::I8(-1), Ty::I8, Const::U8(0xFF)),
+        (Const::U16(0xBEEF), Ty::I16, Const::U16(0xBEEF)),
+        (Const::I16(-2), Ty::I16, Const::U16(0xFFFE)),
+        (Const::U32(42), Ty::I32, Const::U32(42)),
+        (Const::I32(-42), Ty::I32, Const::U32(-42i32 as u32)),
+        (Const::U64(u64::MAX), Ty::I64, Const::U64(u64::MAX)),
+        (Const::I64(i64::MIN), Ty::I64, Const::U64(1 << 63)),
+        (Const::USize(0xFFFF_FFFF), Ty::I32, Const::U32(0xFFFF_FFFF)),
+        (Const::ISize(-1), Ty::I32, Const::U32(u32::MAX)),
+        (Const::Ptr(0x1000), Ty::Ptr, Const::Ptr(0x1000)),
+        (Const::F16(0x3C00), Ty::F16, Const::F16(0x3C00)),
+        (Const::F32(1.5), Ty::F32, Const::F32(1.5)),
+        (Const::F64(-0.25), Ty::F64, Const::F64(-0.25)),
+    ];
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("const_types", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            for (value, ty, decoded) in cases {
+                let inst = entry.push_const(value);
+                assert_eq!(inst.ty(), ty, "{value:?}");
+                let InstData::Const(data) = inst.data(func) else {
+                    panic!("expected a constant for {value:?}");
+                };
+                assert_eq!(data, decoded, "{value:?}");
+            }
+            entry.push_return([]);
+        });
+    });
+}
+
+#[test]
+fn const_small_ints() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("const_small_ints", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [],
+            [
+                FuncParam { ty: Ty::Bool },
+                FuncParam { ty: Ty::I8 },
+                FuncParam { ty: Ty::I16 },
+            ],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let b = entry.push_const(Const::Bool(true));
+            let byte = entry.push_const(Const::I8(-1));
+            let half = entry.push_const(Const::U16(1234));
+            entry.push_return([b, byte, half]);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert!(code.contains("\nconst_small_ints:\n"));
+    assert!(code.contains("1234"));
+}
+
+#[test]
+#[should_panic(expected = "constant USize(4294967296) does not fit in 32 bits on Xr17032")]
+fn const_usize_too_wide() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("const_usize_too_wide", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            func.entry_block().push_const(Const::USize(1 << 32));
+        });
+    });
+}
+
+#[test]
+fn load_store_test() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("load_store_test", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::Ptr }, FuncParam { ty: Ty::Ptr }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let (src, dst) = (func.get_param(0), func.get_param(1));
+            let value = entry.push_load(Ty::I32, src);
+            assert_eq!(value.ty(), Ty::I32);
+            let unaligned = MemFlags { align: Some(2) };
+            entry.push_store_with(dst, value, unaligned);
+            let reloaded = entry.push_load_with(Ty::I16, dst, unaligned);
+            let widened = entry.push_convert(ConvOp::ZeroExt, reloaded, Ty::I32);
+            entry.push_return([widened]);
+
+            let InstData::Load { addr, flags } = value.data(func) else {
+                panic!("expected a load");
+            };
+            assert_eq!(addr, src);
+            assert_eq!(flags, MemFlags { align: Some(4) });
+            let store = entry
+                .insts()
+                .find(|inst| inst.kind() == Opcode::Store)
+                .unwrap();
+            let InstData::Store {
+                addr,
+                value: stored,
+                flags,
+            } = store.data(func)
+            else {
+                panic!("expected a store");
+            };
+            assert_eq!((addr, stored, flags), (dst, value, unaligned));
+            let InstData::Load { addr, flags } = reloaded.data(func) else {
+                panic!("expected a load");
+            };
+            assert_eq!((addr, flags), (dst, unaligned));
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "load_store_test");
+    assert_eq!(count_insts(&code, "ret"), 1);
+}
+
+#[test]
+#[should_panic(expected = "load address has type I32 but must be a pointer")]
+fn load_from_integer() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("load_from_integer", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [FuncParam { ty: Ty::I32 }], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            func.entry_block().push_load(Ty::I32, func.get_param(0));
+        });
+    });
+}
+
+#[test]
+fn stack_slot_test() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("stack_slot_test", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let slot = func.create_stack_slot(4, 4);
+            let entry = func.entry_block();
+            let addr = entry.push_stack_addr(slot);
+            assert_eq!(addr.ty(), Ty::Ptr);
+            entry.push_store(addr, func.get_param(0));
+            let value = entry.push_load(Ty::I32, addr);
+            entry.push_return([value]);
+
+            assert!(matches!(addr.data(func), InstData::StackAddr(_)));
+            let InstData::Load {
+                addr: load_addr, ..
+            } = value.data(func)
+            else {
+                panic!("expected a load");
+            };
+            assert_eq!(load_addr, addr);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "stack_slot_test");
+    assert_eq!(count_insts(&code, "ret"), 1);
+}
+
+#[test]
+#[should_panic(expected = "alignment (3) is not a power of two")]
+fn stack_slot_bad_align() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("stack_slot_bad_align", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            func.create_stack_slot(12, 3);
+        });
+    });
+}
+
+#[test]
+fn data_objects() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let greeting_symbol = module.create_symbol("greeting", SymbolBinding::Local);
+        let greeting = module.create_data(greeting_symbol, *b"hello\0", 1, Section::Rodata);
+        let table_symbol = module.create_symbol("table", SymbolBinding::Global);
+        let table = module.create_data(table_symbol, [0; 8], 4, Section::Data);
+        module.add_reloc(table, 4, greeting);
+        let buffer_symbol = module.create_symbol("buffer", SymbolBinding::Global);
+        let buffer = module.create_data(buffer_symbol, vec![0; 256], 16, Section::Bss);
+        for data in [greeting, table, buffer] {
+            let symbol = SymbolRef::from(data);
+            assert_eq!(unsafe { (*symbol.inner.as_ptr()).kind }, SymbolKind::Data);
+        }
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    for name in ["greeting", "table", "buffer"] {
+        assert_eq!(
+            code.matches(&format!("{name}:")).count(),
+            1,
+            "missing {name}"
+        );
+    }
+    // Local symbols must not be exported.
+    assert!(!code.contains(".global greeting"));
+}
+
+#[test]
+#[should_panic(expected = "relocation at offset 6 is out of bounds of data with length 8")]
+fn data_reloc_out_of_bounds() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let data_symbol = module.create_symbol("data", SymbolBinding::Global);
+        let data = module.create_data(data_symbol, [0; 8], 4, Section::Data);
+        module.add_reloc(data, 6, data);
+    });
+}
+
+#[test]
+#[should_panic(expected = "data in section Bss must be zero")]
+fn data_bss_nonzero() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let data_symbol = module.create_symbol("data", SymbolBinding::Global);
+        module.create_data(data_symbol, [1], 1, Section::Bss);
+    });
+}
+
+#[test]
+fn symbol_addr_test() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let counter_symbol = module.create_symbol("counter", SymbolBinding::Global);
+        let counter = module.create_data(counter_symbol, [0; 4], 4, Section::Bss);
+        let bump_symbol = module.create_symbol("bump", SymbolBinding::Local);
+        let bump_sig = FuncSig::new(CallConv::Jackal, [], []);
+        let callee_sig = bump_sig.clone();
+        let bump = module.create_func(bump_symbol, bump_sig, |func| {
+            let entry = func.entry_block();
+            let addr = entry.push_symbol_addr(counter);
+            assert_eq!(addr.ty(), Ty::Ptr);
+            let value = entry.push_load(Ty::I32, addr);
+            let one = entry.push_const(Const::U32(1));
+            let sum = entry.push_binop(BinOp::IAdd, value, one);
+            entry.push_store(addr, sum);
+            entry.push_return([]);
+            func.get_ref()
+        });
+        let main_symbol = module.create_symbol("main", SymbolBinding::Global);
+        let main_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(main_symbol, main_sig, |func| {
+            let entry = func.entry_block();
+            let bump_addr = entry.push_func_addr(bump);
+            assert!(matches!(bump_addr.data(func), InstData::SymAddr(_)));
+            let returns = entry.push_indirect_call(bump_addr, &callee_sig, []);
+            assert!(returns.is_empty());
+            entry.push_return([]);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_eq!(code.matches("counter:").count(), 1);
+    assert_eq!(count_insts(&code, "bump:"), 1);
+    assert!(!code.contains(".global bump"));
+    assert_global_func(&code, "main");
+    assert_eq!(count_insts(&code, "ret"), 2);
+}
+
+#[test]
+fn extern_data_addr() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let errno_symbol = module.create_symbol("errno", SymbolBinding::Extern);
+        let errno = module.declare_data(errno_symbol);
+        assert_eq!(unsafe { (*errno.inner.as_ptr()).kind }, SymbolKind::Data);
+        let func_symbol = module.create_symbol("get_errno", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], [FuncParam { ty: Ty::I32 }]);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let addr = entry.push_symbol_addr(errno);
+            let InstData::SymAddr(symbol) = addr.data(func) else {
+                panic!("expected a symbol address");
+            };
+            assert_eq!(symbol.inner, errno.inner);
+            let value = entry.push_load(Ty::I32, addr);
+            entry.push_return([value]);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert!(!code.contains("\nerrno:"));
+}
+
+#[test]
+#[should_panic(expected = "declared data symbol cannot have binding Global on Xr17032")]
+fn declare_data_global() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let symbol = module.create_symbol("defined_elsewhere", SymbolBinding::Global);
+        module.declare_data(symbol);
+    });
+}
+
+#[test]
+fn extern_call() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let putchar_symbol = module.create_symbol("putchar", SymbolBinding::Extern);
+        let putchar_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        let putchar = module.declare_func(putchar_symbol, putchar_sig);
+        let main_symbol = module.create_symbol("main", SymbolBinding::Global);
+        let main_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(main_symbol, main_sig, |func| {
+            let entry = func.entry_block();
+            let c = entry.push_const(Const::U32(u32::from(b'!')));
+            entry.push_direct_call(putchar, [c]);
+            entry.push_return([]);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert!(code.starts_with(".extern putchar\n\n"));
+    assert!(!code.contains("\nputchar:"));
+}
+
+#[test]
+#[should_panic(expected = "cannot edit the body of a declared function")]
+fn edit_declared_func() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("elsewhere", SymbolBinding::Extern);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        let func = module.declare_func(func_symbol, func_sig);
+        module.edit_func(func, |func| func.entry_block().push_return([]));
+    });
+}
+
+#[test]
+fn codegen_missing_terminator() {
+    let err = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("unterminated", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let next = func.create_block();
+            entry.push_jump(next);
+            next.push_const(Const::U32(0));
+        });
+        module.codegen().unwrap_err()
+    });
+    assert_eq!(
+        err,
+        IronError {
+            func: "unterminated".into(),
+            block: 1,
+            kind: IronErrorKind::MissingTerminator,
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "in function `unterminated`, block 1: block does not end in a terminator"
+    );
+}
+
+#[test]
+fn codegen_inst_after_terminator() {
+    let err = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("overterminated", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            entry.push_return([]);
+            entry.push_return([]);
+        });
+        module.codegen().unwrap_err()
+    });
+    assert_eq!(err.kind, IronErrorKind::InstAfterTerminator);
+}
+
+#[test]
+fn verify_collects_diagnostics() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("many_problems", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let b1 = func.create_block();
+            let b2 = func.create_block();
+            entry.push_jump(b1);
+            entry.push_const(Const::U32(0));
+            b1.push_jump(b2);
+            let diagnostics = func.verify().unwrap_err();
+            let kinds: Vec<_> = diagnostics
+                .errors
+                .iter()
+                .map(|e| (e.block, &e.kind))
+                .collect();
+            assert_eq!(
+                kinds,
+                [
+                    (0, &IronErrorKind::InstAfterTerminator),
+                    (2, &IronErrorKind::MissingTerminator),
+                ]
+            );
+            assert!(diagnostics.errors.iter().all(|e| e.func == "many_problems"));
+            assert_eq!(diagnostics.ir, func.to_string());
+
+            b2.push_return([]);
+        });
+        let all_diagnostics = module.verify().unwrap_err();
+        assert_eq!(all_diagnostics.len(), 1);
+        assert_eq!(all_diagnostics[0].errors.len(), 1);
+    });
+}
+
+#[test]
+fn verify_branch_to_same_block() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("same_targets", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::Bool }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let exit = func.create_block();
+            entry.push_branch(func.get_param(0), exit, exit);
+            let phi = exit.push_phi(Ty::I32);
+            exit.push_return([phi]);
+            let diagnostics = func.verify().unwrap_err();
+            let kinds: Vec<_> = diagnostics.errors.iter().map(|e| &e.kind).collect();
+            assert_eq!(kinds, [&IronErrorKind::MissingUpsilon { predecessor: 0 }]);
+        });
+    });
+}
+
+#[test]
+fn try_constructors() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let err = module
+            .try_create_symbol("a".repeat(0x1_0000), SymbolBinding::Global)
+            .unwrap_err();
+        assert_eq!(err, BuildError::SymbolTooLong { len: 0x1_0000 });
+
+        let params = std::iter::repeat_n(FuncParam { ty: Ty::I32 }, 0x1_0000);
+        let err = FuncSig::try_new(CallConv::Jackal, params, []).unwrap_err();
+        assert_eq!(err, BuildError::TooManyParams { len: 0x1_0000 });
+
+        let func_symbol = module
+            .try_create_symbol("oopsie", SymbolBinding::SharedImport)
+            .unwrap();
+        let func_sig = FuncSig::try_new(CallConv::Jackal, [], []).unwrap();
+        let err = module
+            .try_create_func(func_symbol, func_sig, |_| ())
+            .unwrap_err();
+        assert_eq!(err, BuildError::FuncBinding(SymbolBinding::SharedImport));
+        assert_eq!(
+            err.to_string(),
+            "function symbol cannot have binding SharedImport"
+        );
+    });
+}
+
+fn build_id_module(module: &Module) {
+    let func_symbol = module.create_symbol("id", SymbolBinding::Global);
+    let func_sig = FuncSig::new(
+        CallConv::Jackal,
+        [FuncParam { ty: Ty::I32 }],
+        [FuncParam { ty: Ty::I32 }],
+    );
+    module.create_func(func_symbol, func_sig, |func| {
+        func.entry_block().push_return([func.get_param(0)]);
+    });
+}
+
+#[test]
+fn emit_asm_to_writer() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        build_id_module(&module);
+        module.codegen().unwrap()
+    });
+    let mut streamed = vec![];
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        build_id_module(&module);
+        module.emit_asm_to(&mut streamed).unwrap();
+    });
+    assert_eq!(std::str::from_utf8(&streamed).unwrap().trim(), code);
+
+    let path = std::env::temp_dir().join(format!("iron-rs-emit-{}.s", std::process::id()));
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        build_id_module(&module);
+        module.emit_asm_to_file(&path).unwrap();
+    });
+    let written = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, streamed);
+}
+
+#[test]
+fn inspect_ir() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("inspect_ir", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let exit = func.create_block();
+            let param = func.get_param(0);
+            let one = entry.push_const(Const::U32(1));
+            let sum = entry.push_binop(BinOp::IAdd, param, one);
+            entry.push_jump(exit);
+            exit.push_return([sum]);
+
+            let blocks: Vec<_> = func.blocks().collect();
+            assert_eq!(blocks, [entry, exit]);
+            // Parameters are instructions at the start of the entry block.
+            let kinds: Vec<_> = entry.insts().map(InstRef::kind).collect();
+            assert_eq!(
+                kinds,
+                [Opcode::Param, Opcode::Const, Opcode::IAdd, Opcode::Jump]
+            );
+            assert_eq!(entry.insts().next(), Some(param));
+            assert!(entry.insts().any(|inst| inst == sum));
+            assert_eq!(sum.inputs().collect::<Vec<_>>(), [param, one]);
+            assert_eq!(sum.block(func), entry);
+            let ret = exit.insts().last().unwrap();
+            assert_eq!(ret.kind(), Opcode::Return);
+            assert_eq!(ret.inputs().collect::<Vec<_>>(), [sum]);
+            assert_eq!(ret.block(func), exit);
+
+            // The start of the entry block is after its parameters.
+            let zero = Cursor::at_start(entry).push_const(Const::U32(0));
+            let insts: Vec<_> = entry.insts().collect();
+            assert_eq!(insts[..3], [param, zero, one]);
+        });
+    });
+}
+
+#[test]
+fn decode_inst_data() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let callee_symbol = module.create_symbol("callee", SymbolBinding::Extern);
+        let callee_sig = FuncSig::new(CallConv::Jackal, [FuncParam { ty: Ty::I32 }], []);
+        let callee = module.declare_func(callee_symbol, callee_sig);
+        let func_symbol = module.create_symbol("decode_inst_data", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::Ptr }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let exit = func.create_block();
+            let addr = func.get_param(0);
+            let loaded = entry.push_load(Ty::I32, addr);
+            let seven = entry.push_const(Const::U32(7));
+            let sum = entry.push_binop(BinOp::IAdd, loaded, seven);
+            let neg = entry.push_unop(UnOp::Neg, sum);
+            entry.push_store_with(addr, neg, MemFlags { align: Some(2) });
+            entry.push_direct_call(callee, [neg]);
+            let cond = entry.push_binop(BinOp::ILt, neg, seven);
+            entry.push_branch(cond, exit, exit);
+            exit.push_return([neg]);
+
+            assert!(matches!(
+                func.get_param(0).data(func),
+                InstData::Param { index: 0 }
+            ));
+            assert!(matches!(seven.data(func), InstData::Const(Const::U32(7))));
+            let InstData::Load {
+                addr: load_addr,
+                flags,
+            } = loaded.data(func)
+            else {
+                panic!("expected a load");
+            };
+            assert_eq!(load_addr, addr);
+            assert_eq!(flags.align, Some(4));
+            let InstData::Binop { op, lhs, rhs } = sum.data(func) else {
+                panic!("expected a binop");
+            };
+            assert!(matches!(op, BinOp::IAdd));
+            assert_eq!((lhs, rhs), (loaded, seven));
+            assert!(matches!(
+                neg.data(func),
+                InstData::Unop { op: UnOp::Neg, value } if value == sum
+            ));
+
+            let insts: Vec<_> = entry.insts().collect();
+            let InstData::Store { value, flags, .. } = insts[5].data(func) else {
+                panic!("expected a store");
+            };
+            assert_eq!(value, neg);
+            assert_eq!(flags.align, Some(2));
+            let InstData::CallDirect { args, .. } = insts[6].data(func) else {
+                panic!("expected a direct call");
+            };
+            assert_eq!(args, [neg]);
+            let InstData::Branch {
+                cond: branch_cond,
+                then_block,
+                else_block,
+            } = insts[8].data(func)
+            else {
+                panic!("expected a branch");
+            };
+            assert_eq!(branch_cond, cond);
+            assert_eq!((then_block, else_block), (exit, exit));
+            assert_eq!(insts[0].kind(), Opcode::Param);
+            assert!(insts[8].kind().is_terminator());
+            assert!(!insts[7].kind().is_terminator());
+
+            let ret = exit.insts().next().unwrap();
+            let InstData::Return { values } = ret.data(func) else {
+                panic!("expected a return");
+            };
+            assert_eq!(values, [neg]);
+        });
+    });
+}
+
+#[test]
+fn replace_and_remove() {
+    // Fold `x + 0` into `x`, as a peephole pass would.
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("add_zero", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::I32 }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let param = func.get_param(0);
+            let zero = entry.push_const(Const::U32(0));
+            let sum = entry.push_binop(BinOp::IAdd, param, zero);
+            entry.push_return([sum]);
+
+            for inst in entry.insts() {
+                let InstData::Binop {
+                    op: BinOp::IAdd,
+                    lhs,
+                    rhs,
+                } = inst.data(func)
+                else {
+                    continue;
+                };
+                if matches!(rhs.data(func), InstData::Const(Const::U32(0))) {
+                    inst.replace_all_uses_with(lhs);
+                    entry.remove_inst(inst);
+                    entry.remove_inst(rhs);
+                }
+            }
+            let kinds: Vec<_> = entry.insts().map(InstRef::kind).collect();
+            assert_eq!(kinds, [Opcode::Param, Opcode::Return]);
+            let ret = entry.insts().last().unwrap();
+            assert_eq!(ret.inputs().collect::<Vec<_>>(), [param]);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "add_zero");
+    assert_eq!(count_insts(&code, "ret"), 1);
+}
+
+#[test]
+#[should_panic(expected = "removed instruction still has users")]
+#[cfg(debug_assertions)]
+fn remove_used_inst() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("remove_used_inst", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], [FuncParam { ty: Ty::I32 }]);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let value = entry.push_const(Const::U32(1));
+            entry.push_return([value]);
+            entry.remove_inst(value);
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "cannot remove a parameter")]
+fn remove_param() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("remove_param", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [FuncParam { ty: Ty::I32 }], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            entry.push_return([]);
+            entry.remove_inst(func.get_param(0));
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "cannot remove a phi which upsilons still write to")]
+fn remove_phi_with_upsilon() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("remove_phi_with_upsilon", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let exit = func.create_block();
+            let phi = exit.push_phi(Ty::I32);
+            let zero = entry.push_const(Const::U32(0));
+            entry.push_upsilon(phi, zero);
+            entry.push_jump(exit);
+            exit.push_return([]);
+            exit.remove_inst(phi);
+        });
+    });
+}
+
+#[test]
+fn cursor_insertion() {
+    let code = Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("cursor_insertion", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], [FuncParam { ty: Ty::I32 }]);
+        module.create_func(func_symbol, func_sig, |func| {
+            let entry = func.entry_block();
+            let exit = func.create_block();
+            entry.push_jump(exit);
+            let phi = exit.push_phi(Ty::I32);
+            let two = exit.push_const(Const::U32(2));
+            let cursor = Cursor::after(func, two);
+            let product = cursor.push_binop(BinOp::IMul, two, phi);
+            Cursor::at_end(exit).push_return([product]);
+
+            // Insert before the existing instructions, in order.
+            let jump = entry.insts().next().unwrap();
+            let cursor = Cursor::before(func, jump);
+            let zero = cursor.push_const(Const::U32(0));
+            cursor.push_upsilon(phi, zero);
+            let one = Cursor::at_start(exit).push_const(Const::U32(1));
+            let sum = cursor.push_binop(BinOp::IAdd, zero, zero);
+            assert_eq!(cursor.block(), entry);
+
+            let entry_insts: Vec<_> = entry.insts().collect();
+            assert_eq!(entry_insts[0], zero);
+            assert_eq!(entry_insts[1].kind(), Opcode::Upsilon);
+            assert_eq!(entry_insts[2..], [sum, jump]);
+            let exit_insts: Vec<_> = exit.insts().collect();
+            assert_eq!(exit_insts[..4], [phi, one, two, product]);
+            assert_eq!(exit_insts[4].kind(), Opcode::Return);
+        });
+        module.codegen().unwrap()
+    });
+    println!("{code}");
+    assert_global_func(&code, "cursor_insertion");
+    assert_eq!(count_insts(&code, ".b2:"), 1);
+    assert_eq!(count_insts(&code, "ret"), 1);
+}
+
+#[test]
+#[should_panic(expected = "cannot insert before a parameter or phi")]
+fn cursor_before_param() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("cursor_before_param", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [FuncParam { ty: Ty::I32 }], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            Cursor::before(func, func.get_param(0));
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "cannot insert before a parameter or phi")]
+fn cursor_between_phis() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("cursor_between_phis", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let exit = func.create_block();
+            let first = exit.push_phi(Ty::I32);
+            exit.push_phi(Ty::I32);
+            Cursor::after(func, first);
+        });
+    });
+}
+
+#[test]
+fn cfg_queries() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("cfg_queries", SymbolBinding::Global);
+        let func_sig = FuncSig::new(
+            CallConv::Jackal,
+            [FuncParam { ty: Ty::Bool }],
+            [FuncParam { ty: Ty::I32 }],
+        );
+        module.create_func(func_symbol, func_sig, |func| {
+            // entry -> (header <-> body), header -> exit, plus an unreachable block jumping to exit.
+            let entry = func.entry_block();
+            let header = func.create_block();
+            let body = func.create_block();
+            let exit = func.create_block();
+            let dead = func.create_block();
+            let cond = func.get_param(0);
+            entry.push_jump(header);
+            header.push_branch(cond, body, exit);
+            body.push_jump(header);
+            let zero = exit.push_const(Const::U32(0));
+            exit.push_return([zero]);
+            dead.push_jump(exit);
+
+            let cfg = Cfg::new(func);
+            assert_eq!(cfg.entry_block(), entry);
+            assert_eq!(cfg.successors(entry), [header]);
+            assert_eq!(cfg.successors(header), [body, exit]);
+            assert_eq!(cfg.successors(exit), []);
+            assert_eq!(cfg.predecessors(header), [entry, body]);
+            assert_eq!(cfg.predecessors(exit), [header, dead]);
+            assert_eq!(cfg.predecessors(entry), []);
+            assert_eq!(
+                cfg.preorder().collect::<Vec<_>>(),
+                [entry, header, body, exit]
+            );
+            assert_eq!(
+                cfg.postorder().collect::<Vec<_>>(),
+                [body, exit, header, entry]
+            );
+            assert_eq!(
+                cfg.reverse_postorder().collect::<Vec<_>>(),
+                [entry, header, exit, body]
+            );
+            assert!(cfg.is_reachable(exit));
+            assert!(!cfg.is_reachable(dead));
+            assert_eq!(cfg.unreachable_blocks().collect::<Vec<_>>(), [dead]);
+        });
+    });
+}
+
+#[test]
+fn cfg_infinite_loop() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("cfg_infinite_loop", SymbolBinding::Global);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.create_func(func_symbol, func_sig, |func| {
+            let b1 = func.entry_block();
+            let b2 = func.create_block();
+            b1.push_jump(b2);
+            b2.push_jump(b1);
+
+            let cfg = Cfg::new(func);
+            assert_eq!(cfg.successors(b2), [b1]);
+            assert_eq!(cfg.predecessors(b1), [b2]);
+            assert_eq!(cfg.reverse_postorder().collect::<Vec<_>>(), [b1, b2]);
+            assert_eq!(cfg.unreachable_blocks().count(), 0);
+        });
+    });
+}
+
+#[test]
+#[should_panic(expected = "declared function symbol cannot have binding SharedImport on Xr17032")]
+fn declare_shared_import_xr17032() {
+    Module::new(Arch::Xr17032, System::Freestanding, |module| {
+        let func_symbol = module.create_symbol("imported", SymbolBinding::SharedImport);
+        let func_sig = FuncSig::new(CallConv::Jackal, [], []);
+        module.declare_func(func_symbol, func_sig);
+    });
+}
@@ -0,0 +1,239 @@
+//! Checking functions for malformed IR before Iron sees them.
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use crate::{
+    InstKindGeneric, Trait, Ty, ffi, inst_is, raw_blocks, raw_inputs, raw_insts, raw_successors,
+    sig_returns, ty_is_float, ty_is_int,
+};
+
+/// Malformed IR found by [`Module::codegen`](crate::Module::codegen), which would otherwise abort inside Iron.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IronError {
+    /// The name of the function containing the error.
+    pub func: String,
+    /// The index of the block containing the error, in layout order.
+    pub block: usize,
+    pub kind: IronErrorKind,
+}
+
+impl fmt::Display for IronError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { func, block, kind } = self;
+        write!(f, "in function `{func}`, block {block}: {kind}")
+    }
+}
+
+impl Error for IronError {}
+
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IronErrorKind {
+    /// The block does not end in a terminator.
+    MissingTerminator,
+    /// An instruction follows the block's terminator.
+    InstAfterTerminator,
+    /// A return has the wrong number of values for the function's signature.
+    ReturnArity { expected: u16, found: u16 },
+    /// A return value has the wrong type for the function's signature.
+    ReturnType { index: u16, expected: Ty, found: Ty },
+    /// An instruction requiring inputs of the same type has inputs of different types.
+    InputTypes { found: Vec<Ty> },
+    /// An instruction requiring integer inputs has an input of another type.
+    NonIntInput { found: Ty },
+    /// An instruction requiring float inputs has an input of another type.
+    NonFloatInput { found: Ty },
+    /// An instruction's type does not match the type its operation produces.
+    OutputType { expected: Ty, found: Ty },
+    /// A parameter index is out of range for the function's signature.
+    ParamOutOfRange { index: u16, len: u16 },
+    /// The block's terminator refers to a block of another function.
+    ForeignBlock,
+    /// A phi is in a block with no predecessors, so it can never be written.
+    PhiWithoutPredecessors,
+    /// A phi has no upsilon in the predecessor block with this index.
+    MissingUpsilon { predecessor: usize },
+}
+
+impl fmt::Display for IronErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingTerminator => f.write_str("block does not end in a terminator"),
+            Self::InstAfterTerminator => f.write_str("instruction after terminator"),
+            Self::ReturnArity { expected, found } => {
+                write!(f, "expected {expected} return values but found {found}")
+            }
+            Self::ReturnType {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "return value {index} has type {found:?} but the signature expects {expected:?}"
+            ),
+            Self::InputTypes { found } => write!(f, "mismatched input types {found:?}"),
+            Self::NonIntInput { found } => write!(f, "expected integer input but found {found:?}"),
+            Self::NonFloatInput { found } => write!(f, "expected float input but found {found:?}"),
+            Self::OutputType { expected, found } => {
+                write!(f, "expected output type {expected:?} but found {found:?}")
+            }
+            Self::ParamOutOfRange { index, len } => write!(
+                f,
+                "parameter index out of bounds: the len is {len} but the index is {index}"
+            ),
+            Self::ForeignBlock => f.write_str("terminator refers to a block of another function"),
+            Self::PhiWithoutPredecessors => f.write_str("phi in a block with no predecessors"),
+            Self::MissingUpsilon { predecessor } => {
+                write!(
+                    f,
+                    "phi is missing an upsilon from predecessor block {predecessor}"
+                )
+            }
+        }
+    }
+}
+
+/// Everything [`Func::verify`](crate::Func::verify) found wrong with a function.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostics {
+    pub errors: Vec<IronError>,
+    /// The function's IR as printed by Iron.
+    pub ir: String,
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for error in &self.errors {
+            writeln!(f, "{error}")?;
+        }
+        f.write_str(&self.ir)
+    }
+}
+
+impl Error for Diagnostics {}
+
+/// Check every invariant Iron relies on but does not check itself, returning the index of the offending block with each error.
+///
+/// # Safety
+/// `func` must point to a valid [`ffi::Func`].
+pub(crate) unsafe fn verify_func(func: *const ffi::Func) -> Vec<(usize, IronErrorKind)> {
+    let blocks: Vec<_> = unsafe { raw_blocks(func) }.collect();
+    let block_index: HashMap<_, _> = (0..).zip(&blocks).map(|(i, &block)| (block, i)).collect();
+    let mut predecessors = HashMap::<_, Vec<_>>::new();
+    let mut errors = vec![];
+    for (i, &block) in blocks.iter().enumerate() {
+        let insts: Vec<_> = unsafe { raw_insts(block) }.collect();
+        let terminator = insts
+            .iter()
+            .position(|&inst| unsafe { (*inst).kind.has_trait(Trait::TERMINATOR) });
+        match terminator {
+            None => errors.push((i, IronErrorKind::MissingTerminator)),
+            Some(t) if t + 1 != insts.len() => errors.push((i, IronErrorKind::InstAfterTerminator)),
+            Some(_) => {}
+        }
+        let successors = unsafe { raw_successors(block) };
+        for (j, &successor) in successors.iter().enumerate() {
+            // A branch to the same block twice is still only one predecessor.
+            if successors[..j].contains(&successor) {
+                continue;
+            }
+            if unsafe { (*successor).func }.cast_const() != func {
+                errors.push((i, IronErrorKind::ForeignBlock));
+            } else {
+                predecessors.entry(successor).or_default().push(block);
+            }
+        }
+        for inst in insts {
+            if let Err(kind) = unsafe { check_inst(func, inst) } {
+                errors.push((i, kind));
+            }
+        }
+    }
+
+    // Phis are only checked once all predecessors are known.
+    for (i, &block) in blocks.iter().enumerate() {
+        let block_predecessors = predecessors.get(&block).map_or(&[][..], Vec::as_slice);
+        for phi in unsafe { raw_insts(block) } {
+            if !unsafe { inst_is(phi, InstKindGeneric::Phi) } {
+                continue;
+            }
+            if block_predecessors.is_empty() {
+                errors.push((i, IronErrorKind::PhiWithoutPredecessors));
+            }
+            for &predecessor in block_predecessors {
+                let has_upsilon = unsafe { raw_insts(predecessor) }.any(|inst| unsafe {
+                    inst_is(inst, InstKindGeneric::Upsilon)
+                        && (*inst.cast::<ffi::Inst<ffi::InstUpsilon>>()).extra.phi == phi
+                });
+                if !has_upsilon {
+                    let predecessor = block_index[&predecessor];
+                    errors.push((i, IronErrorKind::MissingUpsilon { predecessor }));
+                }
+            }
+        }
+    }
+    errors
+}
+
+/// # Safety
+/// `inst` must point to a valid [`ffi::Inst`] in `func`.
+unsafe fn check_inst(func: *const ffi::Func, inst: *mut ffi::Inst) -> Result<(), IronErrorKind> {
+    let sig = unsafe { (*func).sig };
+    let kind = unsafe { (*inst).kind };
+    let ty = unsafe { (*inst).ty };
+    let input_tys: Vec<_> = unsafe { raw_inputs(inst) }
+        .iter()
+        .map(|&input| unsafe { (*input).ty })
+        .collect();
+    if kind.has_trait(Trait::SAME_INPUT_TYS) && input_tys.windows(2).any(|pair| pair[0] != pair[1])
+    {
+        return Err(IronErrorKind::InputTypes { found: input_tys });
+    }
+    let non_int = input_tys.iter().find(|&&input_ty| !ty_is_int(input_ty));
+    if let Some(&found) = non_int.filter(|_| kind.has_trait(Trait::INT_INPUT_TYS)) {
+        return Err(IronErrorKind::NonIntInput { found });
+    }
+    let non_float = input_tys.iter().find(|&&input_ty| !ty_is_float(input_ty));
+    if let Some(&found) = non_float.filter(|_| kind.has_trait(Trait::FLT_INPUT_TYS)) {
+        return Err(IronErrorKind::NonFloatInput { found });
+    }
+    let expected_ty = if kind.has_trait(Trait::BOOL_OUT_TY) {
+        Some(Ty::Bool)
+    } else if kind.has_trait(Trait::SAME_IN_OUT_TY) {
+        input_tys.first().copied()
+    } else {
+        None
+    };
+    if let Some(expected) = expected_ty.filter(|&expected| expected != ty) {
+        return Err(IronErrorKind::OutputType {
+            expected,
+            found: ty,
+        });
+    }
+    if unsafe { inst_is(inst, InstKindGeneric::Param) } {
+        let index = unsafe { (*inst.cast::<ffi::Inst<ffi::InstParam>>()).extra.index };
+        let len = unsafe { (*sig).param_len };
+        if index >= len {
+            return Err(IronErrorKind::ParamOutOfRange { index, len });
+        }
+    }
+    if unsafe { inst_is(inst, InstKindGeneric::Return) } {
+        let returns = unsafe { sig_returns(sig) };
+        if input_tys.len() != returns.len() {
+            return Err(IronErrorKind::ReturnArity {
+                expected: unsafe { (*sig).return_len },
+                found: u16::try_from(input_tys.len()).unwrap_or(u16::MAX),
+            });
+        }
+        for (index, (&found, expected)) in (0..).zip(input_tys.iter().zip(returns)) {
+            if found != expected.ty {
+                return Err(IronErrorKind::ReturnType {
+                    index,
+                    expected: expected.ty,
+                    found,
+                });
+            }
+        }
+    }
+    Ok(())
+}